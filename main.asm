@@ -1,32 +1,34 @@
-
-ldi x ff06
-sto x ff04
-
-ldi x 1
-mov y
-
-loop:
-
-swp
-add
-
-sto x ff00
-sto y ff02
-
-lod x ff04
-str y
-
-ldi y 2
-add
-sto x ff04
-
-jz done
-
-lod x ff00
-lod y ff02
-
-jmp loop
-
-done:
-
-hlt
+; Writes the Fibonacci sequence into memory from ff06 upwards
+; until the write pointer wraps around to 0000.
+
+    ldi x, 0xff06     ; write pointer
+    sto x, 0xff04
+
+    ldi x, 1          ; the first two numbers
+    mov y
+
+loop:
+
+    swp
+    add               ; x = x + y
+
+    sto x, 0xff00     ; save both numbers
+    sto y, 0xff02
+
+    lod x, 0xff04     ; store y at the write pointer
+    str y
+
+    ldi y, 2          ; advance the write pointer
+    add
+    sto x, 0xff04
+
+    jz done           # stop once it wraps
+
+    lod x, 0xff00
+    lod y, 0xff02
+
+    jmp loop
+
+done:
+
+    hlt
@@ -0,0 +1,628 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::bus::Region;
+use crate::cpu::Cpu;
+use crate::isa::{self, Instruction, Operand};
+
+/// A problem found in the source, `line` and `column` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {} `{}`", self.line, self.column, self.message, self.token)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An instruction name, stored uppercase.
+    Mnemonic(String),
+    /// An assembler directive such as `.org`, stored lowercase with its dot.
+    Directive(String),
+    Register(u8),
+    Number(u16),
+    /// A double quoted string with its escapes already resolved.
+    Str(Vec<u8>),
+    /// A name used as an operand, resolved through the symbol table in the second pass.
+    LabelRef(String),
+    /// An operand built from several values and operators, such as `BUF+4*2`.
+    Expr(Expr),
+    /// A bracketed register plus an offset, `[X+4]` and `[BUF+Y]` are both stored as a register and an offset.
+    Indexed(u8, Expr),
+    /// A name followed by `:`, defines a label at the current address.
+    LabelDef(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(i64),
+    Name(String),
+    /// `-` negates, `>` takes the high byte and `<` the low byte.
+    Unary(char, Box<Expr>),
+    /// One of `+`, `-`, `*`, `/` or `%`.
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+/// A run of assembled bytes that belongs at `origin`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Everything `assemble` produces.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Program {
+    pub segments: Vec<Segment>,
+    /// The ranges marked with `.region`, in source order so later ones win where they overlap.
+    pub regions: Vec<(RangeInclusive<usize>, Region)>,
+}
+
+impl Program {
+    /// Copies the segments into memory, then marks the regions on the bus.
+    pub fn load(&self, cpu: &mut Cpu) {
+        for s in &self.segments {
+            cpu.load(s.origin as usize, &s.bytes);
+        }
+        for (range, region) in &self.regions {
+            cpu.bus_mut().protect(range.clone(), *region);
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ','
+}
+
+fn is_comment(c: char) -> bool {
+    c == ';' || c == '#'
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn register(s: &str) -> Option<u8> {
+    let s = s.to_uppercase();
+    let r = match s.as_str() {
+        "R0" => 0,
+        "R1" => 1,
+        _ => isa::REGISTER_NAMES.iter().position(|&name| name == s)?,
+    };
+    Some(r as u8)
+}
+
+/// Splits a line into words, keeping the 1-based column of each. Quoted strings and characters are kept whole and a `;` or `#` outside of one ends the line.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let chars = line.char_indices().collect::<Vec<_>>();
+    let byte = |i: usize| chars.get(i).map_or(line.len(), |&(b, _)| b);
+
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if is_separator(c) { i += 1; continue }
+        if is_comment(c) { break }
+
+        let start = i;
+        while i < chars.len() && !is_separator(chars[i].1) && !is_comment(chars[i].1) {
+            let quote = chars[i].1;
+            i += 1;
+            if quote == '"' || quote == '\'' {
+                while i < chars.len() && chars[i].1 != quote {
+                    if chars[i].1 == '\\' { i += 1 }
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+            }
+        }
+        words.push((start + 1, &line[byte(start)..byte(i)]));
+    }
+    words
+}
+
+/// Resolves the escapes in the text between a pair of quotes.
+fn unescape(s: &str, quote: char) -> Result<Vec<u8>, &'static str> {
+    let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)).filter(|_| s.len() >= 2) else {
+        return Err(if quote == '"' {"unterminated string"} else {"unterminated character"})
+    };
+
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                _ => return Err("unknown escape sequence"),
+            }
+        }
+        else { c };
+        if !c.is_ascii() { return Err("strings may only contain ASCII characters") }
+        bytes.push(c as u8);
+    }
+    Ok(bytes)
+}
+
+/// Parses a literal: decimal, `0x`/`$` hex, `0b` binary or a `'c'` character.
+fn number(s: &str) -> Result<i64, &'static str> {
+    let parsed = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).or_else(|| s.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16)
+    }
+    else if let Some(bin) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        i64::from_str_radix(bin, 2)
+    }
+    else if s.starts_with('\'') {
+        let bytes = unescape(s, '\'')?;
+        return if bytes.len() == 1 { Ok(bytes[0] as i64) } else { Err("expected a single character") }
+    }
+    else if s.chars().all(|c| c.is_ascii_digit()) {
+        s.parse()
+    }
+    else {
+        return Err("invalid number, hex needs a 0x or $ prefix")
+    };
+    parsed.ok().filter(|&n| n <= 0xffff).ok_or("invalid number")
+}
+
+/// Recursive descent parser for operand expressions, which may not contain whitespace.
+struct Parser<'a> {
+    s: &'a str,
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.i..].chars().next()
+    }
+
+    fn sum(&mut self) -> Result<Expr, &'static str> {
+        let mut e = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.i += 1;
+            e = Expr::Binary(op, Box::new(e), Box::new(self.product()?));
+        }
+        Ok(e)
+    }
+
+    fn product(&mut self) -> Result<Expr, &'static str> {
+        let mut e = self.unary()?;
+        while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+            self.i += 1;
+            e = Expr::Binary(op, Box::new(e), Box::new(self.unary()?));
+        }
+        Ok(e)
+    }
+
+    fn unary(&mut self) -> Result<Expr, &'static str> {
+        match self.peek() {
+            Some(op @ ('-' | '>' | '<')) => {
+                self.i += 1;
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, &'static str> {
+        let rest = &self.s[self.i..];
+        match self.peek() {
+            Some('(') => {
+                self.i += 1;
+                let e = self.sum()?;
+                if self.peek() != Some(')') { return Err("expected `)`") }
+                self.i += 1;
+                Ok(e)
+            }
+            Some('\'') => {
+                let mut end = 1;
+                let mut chars = rest.char_indices().skip(1);
+                while let Some((i, c)) = chars.next() {
+                    if c == '\\' { chars.next(); continue }
+                    if c == '\'' { end = i + 1; break }
+                }
+                if end == 1 { return Err("unterminated character") }
+                self.i += end;
+                Ok(Expr::Number(number(&rest[..end])?))
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '$' => {
+                let end = rest.char_indices().skip(1)
+                    .find(|&(_, c)| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .map_or(rest.len(), |(i, _)| i);
+                let word = &rest[..end];
+                self.i += end;
+                if c.is_ascii_digit() || c == '$' { Ok(Expr::Number(number(word)?)) }
+                else { Ok(Expr::Name(word.to_owned())) }
+            }
+            _ => Err("expected a value"),
+        }
+    }
+}
+
+fn parse_expr(s: &str) -> Result<Expr, &'static str> {
+    let mut parser = Parser { s, i: 0 };
+    let e = parser.sum()?;
+    if parser.i < s.len() { return Err("unexpected character in expression") }
+    Ok(e)
+}
+
+/// Parses the inside of an indexed operand: a register alone, a register followed by `+` or `-` and an offset, or
+/// an offset followed by `+` and a register.
+fn indexed(s: &str) -> Result<(u8, Expr), &'static str> {
+    if let Some(r) = register(s) { return Ok((r, Expr::Number(0))) }
+
+    let split = s.find(['+', '-']).unwrap_or(s.len());
+    if let Some(r) = register(&s[..split]) {
+        let offset = &s[split..];
+        return Ok((r, parse_expr(offset.strip_prefix('+').unwrap_or(offset))?))
+    }
+    if let Some((offset, r)) = s.rsplit_once('+').and_then(|(o, r)| Some((o, register(r)?))) {
+        return Ok((r, parse_expr(offset)?))
+    }
+    Err("expected a register in brackets, such as [X+4] or [BUF+Y]")
+}
+
+fn tokenize_line(l: usize, line: &str, errors: &mut Vec<AsmError>) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    for (column, word) in words(line) {
+        let error = |message: &str| AsmError { line: l + 1, column, token: word.to_owned(), message: message.to_owned() };
+        let has_head = tokens.iter().any(|t| matches!(t.kind, TokenKind::Mnemonic(_) | TokenKind::Directive(_)));
+
+        let kind = if let Some(name) = word.strip_suffix(':').filter(|_| !has_head) {
+            if !is_identifier(name) { errors.push(error("invalid label name")); continue }
+            if register(name).is_some() { errors.push(error("label name is reserved for a register")); continue }
+            TokenKind::LabelDef(name.to_owned())
+        }
+        else if !has_head {
+            if word.starts_with('.') { TokenKind::Directive(word.to_lowercase()) }
+            else { TokenKind::Mnemonic(word.to_uppercase()) }
+        }
+        else if word.starts_with('"') {
+            match unescape(word, '"') {
+                Ok(bytes) => TokenKind::Str(bytes),
+                Err(message) => { errors.push(error(message)); continue }
+            }
+        }
+        else if let Some(r) = register(word) {
+            TokenKind::Register(r)
+        }
+        else if let Some(inner) = word.strip_prefix('[') {
+            let Some(inner) = inner.strip_suffix(']') else { errors.push(error("expected `]`")); continue };
+            match indexed(inner) {
+                Ok((r, offset)) => TokenKind::Indexed(r, offset),
+                Err(message) => { errors.push(error(message)); continue }
+            }
+        }
+        else {
+            match parse_expr(word) {
+                Ok(Expr::Number(n)) => TokenKind::Number(n as u16),
+                Ok(Expr::Name(name)) => TokenKind::LabelRef(name),
+                Ok(e) => TokenKind::Expr(e),
+                Err(message) => { errors.push(error(message)); continue }
+            }
+        };
+
+        tokens.push(Token { kind, line: l + 1, column, text: word.to_owned() });
+    }
+    tokens
+}
+
+fn error(token: &Token, message: &str) -> AsmError {
+    AsmError {
+        line: token.line,
+        column: token.column,
+        token: token.text.clone(),
+        message: message.to_owned(),
+    }
+}
+
+fn eval(e: &Expr, symbols: &HashMap<&str, u16>) -> Result<i64, String> {
+    Ok(match e {
+        Expr::Number(n) => *n,
+        Expr::Name(name) => match symbols.get(name.as_str()) {
+            Some(&n) => n as i64,
+            None => return Err(format!("undefined label `{name}`")),
+        },
+        Expr::Unary(op, e) => {
+            let n = eval(e, symbols)?;
+            match op {
+                '-' => n.wrapping_neg(),
+                '>' => (n as u16 >> 8) as i64,
+                _ => (n as u16 & 0xff) as i64,
+            }
+        }
+        Expr::Binary(op, a, b) => {
+            let (a, b) = (eval(a, symbols)?, eval(b, symbols)?);
+            match op {
+                '+' => a.wrapping_add(b),
+                '-' => a.wrapping_sub(b),
+                '*' => a.wrapping_mul(b),
+                _ if b == 0 => return Err("division by zero".to_owned()),
+                '/' => a.wrapping_div(b),
+                _ => a.wrapping_rem(b),
+            }
+        }
+    })
+}
+
+fn resolve(token: &Token, symbols: &HashMap<&str, u16>) -> Result<i64, AsmError> {
+    match &token.kind {
+        TokenKind::Number(n) => Ok(*n as i64),
+        TokenKind::LabelRef(name) => eval(&Expr::Name(name.clone()), symbols).map_err(|_| error(token, "undefined label")),
+        TokenKind::Expr(e) | TokenKind::Indexed(_, e) => eval(e, symbols).map_err(|message| error(token, &message)),
+        _ => Err(error(token, "expected a value")),
+    }
+}
+
+fn value(token: &Token, symbols: &HashMap<&str, u16>) -> Result<u16, AsmError> {
+    let n = resolve(token, symbols)?;
+    if !(-0x8000..=0xffff).contains(&n) { return Err(error(token, "value out of range, expected a 16 bit value")) }
+    Ok(n as u16)
+}
+
+fn byte(token: &Token, symbols: &HashMap<&str, u16>) -> Result<u8, AsmError> {
+    let n = resolve(token, symbols)?;
+    if !(-0x80..=0xff).contains(&n) { return Err(error(token, "value out of range, expected an 8 bit value")) }
+    Ok(n as u8)
+}
+
+/// Picks the instruction a mnemonic stands for, the indexed forms share their names with the plain ones. Falls
+/// back to the first form so the operands can be reported against it.
+fn instruction(mnemonic: &str, args: &[Token]) -> Option<&'static Instruction> {
+    let fits = |inst: &&Instruction| inst.operands.len() == args.len() && inst.operands.iter().zip(args)
+        .all(|(o, a)| (*o == Operand::Indexed) == matches!(a.kind, TokenKind::Indexed(..)));
+
+    isa::INSTRUCTIONS.iter()
+        .filter(|i| i.mnemonic.eq_ignore_ascii_case(mnemonic))
+        .find(fits)
+        .or_else(|| isa::by_mnemonic(mnemonic))
+}
+
+/// Works out how many bytes a directive emits, `.org` is handled by the caller.
+fn directive_size(head: &Token, args: &[Token], symbols: &HashMap<&str, u16>) -> Result<usize, AsmError> {
+    let TokenKind::Directive(name) = &head.kind else { unreachable!() };
+    let strings = |terminator: usize| args.iter()
+        .map(|a| match &a.kind { TokenKind::Str(s) => s.len() + terminator, _ => 1 })
+        .sum::<usize>();
+
+    match name.as_str() {
+        ".db" => Ok(strings(0)),
+        ".dw" => Ok(args.len() * 2),
+        ".string" => Ok(strings(1)),
+        ".fill" => match args {
+            [count] | [count, _] => Ok(value(count, symbols)? as usize),
+            _ => Err(error(head, "expected a count and an optional value")),
+        },
+        _ => Err(error(head, "unknown directive")),
+    }
+}
+
+/// Reads the operands of `.region kind start end`.
+fn region(head: &Token, args: &[Token], symbols: &HashMap<&str, u16>) -> Result<(RangeInclusive<usize>, Region), AsmError> {
+    let [kind, start, end] = args else { return Err(error(head, "expected a region kind, a start and an end")) };
+    let region = match &kind.kind {
+        TokenKind::LabelRef(name) => Region::from_name(&name.to_lowercase()),
+        _ => None,
+    };
+    let Some(region) = region else { return Err(error(kind, "expected ram, rom, data or noaccess")) };
+
+    let (first, last) = (value(start, symbols)? as usize, value(end, symbols)? as usize);
+    if last < first { return Err(error(end, "region ends before it starts")) }
+    Ok((first..=last, region))
+}
+
+fn directive_bytes(head: &Token, args: &[Token], symbols: &HashMap<&str, u16>, errors: &mut Vec<AsmError>) -> Vec<u8> {
+    let TokenKind::Directive(name) = &head.kind else { unreachable!() };
+    let mut bytes = Vec::new();
+
+    match name.as_str() {
+        ".db" | ".string" => {
+            for arg in args {
+                match &arg.kind {
+                    TokenKind::Str(s) => {
+                        bytes.extend(s);
+                        if name == ".string" { bytes.push(0) }
+                    }
+                    _ if name == ".string" => errors.push(error(arg, "expected a string")),
+                    _ => match byte(arg, symbols) {
+                        Ok(n) => bytes.push(n),
+                        Err(e) => errors.push(e),
+                    },
+                }
+            }
+        }
+        ".dw" => {
+            for arg in args {
+                match value(arg, symbols) {
+                    Ok(n) => bytes.extend(n.to_be_bytes()),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        ".fill" => {
+            let count = args.first().and_then(|a| value(a, symbols).ok()).unwrap_or(0) as usize;
+            let fill = match args.get(1).map(|a| byte(a, symbols)) {
+                Some(Ok(n)) => n,
+                Some(Err(e)) => { errors.push(e); 0 }
+                None => 0,
+            };
+            bytes.resize(count, fill);
+        }
+        _ => {}
+    }
+    bytes
+}
+
+/// Assembles the source of a `.asm` file into machine code. Output starts at address 0 unless moved with `.org`.
+///
+/// Numbers are decimal unless written as `0x1f`/`$1f` hex, `0b101` binary or `'A'` characters. Any other name must
+/// be a label or a constant, so `dead` is an undefined label rather than 0xDEAD. Operands may be expressions without
+/// whitespace, such as `BUF+4*2`, `-1`, `>addr` (high byte) or `<addr` (low byte).
+/// Indexed operands put a register and an optional offset in brackets, as in `[Y]`, `[X+4]` or `[BUF+Y]`.
+///
+/// Directives:
+/// - `.org addr` continues output at `addr`
+/// - `.equ NAME value` defines a constant, `value` may only use names defined above it
+/// - `.db` emits bytes and strings, `.dw` emits big-endian words
+/// - `.string "text"` emits the text followed by a zero byte
+/// - `.fill count [value]` emits `count` copies of `value`, 0 by default
+/// - `.region kind start end` marks `start` to `end` inclusive as `ram`, `rom`, `data` or `noaccess`, see `Region`
+pub fn assemble(asm: String) -> Result<Program, Vec<AsmError>> {
+    let mut errors = Vec::new();
+
+    // Lines that failed to tokenize still count towards label addresses but aren't encoded.
+    let mut lines = Vec::new();
+    for (l, line) in asm.lines().enumerate() {
+        let count = errors.len();
+        let tokens = tokenize_line(l, line, &mut errors);
+        lines.push((tokens, errors.len() == count));
+    }
+
+    let mut symbols: HashMap<&str, u16> = HashMap::new();
+
+    // Where each line's output starts, filled in by the first pass.
+    let mut addresses = vec![0; lines.len()];
+
+    let mut addr = 0;
+    for ((tokens, _), start) in lines.iter().zip(&mut addresses) {
+        let head = tokens.iter().position(|t| !matches!(t.kind, TokenKind::LabelDef(_)));
+
+        if let Some(i) = head {
+            match (&tokens[i].kind, &tokens[i + 1..]) {
+                (TokenKind::Directive(name), [origin]) if name == ".org" => match value(origin, &symbols) {
+                    Ok(n) => addr = n as usize,
+                    Err(e) => errors.push(e),
+                },
+                (TokenKind::Directive(name), [constant, n]) if name == ".equ" => match (&constant.kind, value(n, &symbols)) {
+                    (TokenKind::LabelRef(name), Ok(n)) => if symbols.insert(name, n).is_some() {
+                        errors.push(error(constant, "duplicate label"));
+                    },
+                    (TokenKind::LabelRef(_), Err(e)) => errors.push(e),
+                    _ => errors.push(error(constant, "expected a name")),
+                },
+                (TokenKind::Directive(name), _) if name == ".org" => errors.push(error(&tokens[i], "expected 1 operand")),
+                (TokenKind::Directive(name), _) if name == ".equ" => errors.push(error(&tokens[i], "expected a name and a value")),
+                _ => {}
+            }
+        }
+        *start = addr;
+
+        for token in tokens {
+            match &token.kind {
+                TokenKind::LabelDef(name) if symbols.insert(name, addr as u16).is_some() => {
+                    errors.push(error(token, "duplicate label"));
+                }
+                TokenKind::Mnemonic(mnemonic) => match instruction(mnemonic, &tokens[head.unwrap() + 1..]) {
+                    Some(inst) => addr += inst.size(),
+                    None => errors.push(error(token, "unknown mnemonic")),
+                },
+                TokenKind::Directive(name) if name != ".org" && name != ".equ" && name != ".region" => {
+                    match directive_size(token, &tokens[head.unwrap() + 1..], &symbols) {
+                        Ok(size) => addr += size,
+                        Err(e) => errors.push(e),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut regions = Vec::new();
+    let mut used = vec![false; 0x1_0000];
+
+    for ((tokens, _), &start) in lines.iter().zip(&addresses).filter(|((_, valid), _)| *valid) {
+        let Some(i) = tokens.iter().position(|t| !matches!(t.kind, TokenKind::LabelDef(_))) else {continue};
+        let head = &tokens[i];
+        let args = &tokens[i + 1..];
+
+        let mut bytes = Vec::new();
+        match &head.kind {
+            TokenKind::Directive(name) if name == ".org" || name == ".equ" => continue,
+            TokenKind::Directive(name) if name == ".region" => {
+                match region(head, args, &symbols) {
+                    Ok(r) => regions.push(r),
+                    Err(e) => errors.push(e),
+                }
+                continue;
+            }
+            TokenKind::Directive(_) => bytes = directive_bytes(head, args, &symbols, &mut errors),
+            TokenKind::Mnemonic(mnemonic) => {
+                let Some(inst) = instruction(mnemonic, args) else {continue};
+                let operands = inst.operands;
+
+                if args.len() != operands.len() {
+                    let token = args.get(operands.len()).unwrap_or(head);
+                    errors.push(error(token, &format!("expected {} operand(s), found {}", operands.len(), args.len())));
+                    continue;
+                }
+
+                bytes.push(inst.opcode);
+                for (arg, operand) in args.iter().zip(operands) {
+                    match (operand, &arg.kind) {
+                        (Operand::Register, TokenKind::Register(r)) => bytes.push(*r),
+                        (Operand::Register, _) => errors.push(error(arg, "bad register, expected X, Y or R0 to R7")),
+                        (Operand::Indexed, TokenKind::Indexed(r, _)) => match value(arg, &symbols) {
+                            Ok(n) => { bytes.push(*r); bytes.extend(n.to_be_bytes()) }
+                            Err(e) => errors.push(e),
+                        },
+                        (Operand::Indexed, _) => errors.push(error(arg, "expected an indexed operand such as [X+4]")),
+                        (_, TokenKind::Indexed(..)) => errors.push(error(arg, "no indexed form of this instruction")),
+                        (Operand::Value | Operand::Pointer, _) => match value(arg, &symbols) {
+                            Ok(n) => bytes.extend(n.to_be_bytes()),
+                            Err(e) => errors.push(e),
+                        },
+                    }
+                }
+            }
+            _ => continue,
+        }
+
+        if start + bytes.len() > 0x1_0000 {
+            errors.push(error(head, "output runs past the end of memory"));
+            continue;
+        }
+        if used[start..start + bytes.len()].iter().any(|&u| u) {
+            errors.push(error(head, "output overlaps earlier code or data"));
+            continue;
+        }
+        used[start..start + bytes.len()].fill(true);
+
+        match segments.last_mut() {
+            Some(s) if s.origin as usize + s.bytes.len() == start => s.bytes.extend(bytes),
+            _ if bytes.is_empty() => {}
+            _ => segments.push(Segment { origin: start as u16, bytes }),
+        }
+    }
+
+    if errors.is_empty() { Ok(Program { segments, regions }) }
+    else {
+        errors.sort_by_key(|e| (e.line, e.column));
+        Err(errors)
+    }
+}
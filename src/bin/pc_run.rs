@@ -0,0 +1,144 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pc_visualizer::console::Console;
+use pc_visualizer::keyboard::{Keyboard, STATUS_READY};
+use pc_visualizer::random::Random;
+use pc_visualizer::sound::Sound;
+use pc_visualizer::{assemble, isa, Cpu};
+
+const USAGE: &str = "Usage: pc_run <file.asm> [--steps N] [--dump START:END] [--keys TEXT] [--seed N]
+
+Runs the program without a window until HLT or until N steps (default 100000) have been executed,
+then prints the tones it played, what it wrote to the console, the registers and the memory from
+START to END inclusive (hex, default FF00:FFFF). The characters of TEXT are typed on the keyboard
+one at a time, each once the program has taken the one before. The random number port is seeded
+with N, or from the clock if it isn't given, and the seed is printed so the run can be repeated.";
+
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let (start, end) = s.split_once(':')?;
+    let start = usize::from_str_radix(start, 16).ok()?;
+    let end = usize::from_str_radix(end, 16).ok()?;
+    if start > end || end > 0xffff {return None}
+    Some((start, end))
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let mut file = None;
+    let mut max_steps = 100_000;
+    let mut dump = (0xff00, 0xffff);
+    let mut keys = String::new();
+    let mut seed = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--steps" => {
+                i += 1;
+                match args.get(i).and_then(|s| s.parse().ok()) {
+                    Some(n) => max_steps = n,
+                    None => { eprintln!("{USAGE}"); exit(2) }
+                }
+            }
+            "--dump" => {
+                i += 1;
+                match args.get(i).and_then(|s| parse_range(s)) {
+                    Some(range) => dump = range,
+                    None => { eprintln!("{USAGE}"); exit(2) }
+                }
+            }
+            "--keys" => {
+                i += 1;
+                match args.get(i) {
+                    Some(text) => keys = text.clone(),
+                    None => { eprintln!("{USAGE}"); exit(2) }
+                }
+            }
+            "--seed" => {
+                i += 1;
+                match args.get(i).and_then(|s| s.parse().ok()) {
+                    Some(n) => seed = Some(n),
+                    None => { eprintln!("{USAGE}"); exit(2) }
+                }
+            }
+            "-h" | "--help" => { println!("{USAGE}"); return }
+            path if file.is_none() => file = Some(path.to_owned()),
+            _ => { eprintln!("{USAGE}"); exit(2) }
+        }
+        i += 1;
+    }
+
+    let Some(file) = file else { eprintln!("{USAGE}"); exit(2) };
+
+    let source = match fs::read_to_string(&file) {
+        Ok(s) => s,
+        Err(e) => { eprintln!("{file}: {e}"); exit(2) }
+    };
+
+    let program = match assemble(source) {
+        Ok(program) => program,
+        Err(errors) => {
+            for e in errors { eprintln!("{file}:{e}") }
+            exit(2)
+        }
+    };
+
+    let mut cpu = Cpu::new();
+    program.load(&mut cpu);
+
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u16));
+    if let Some(random) = cpu.bus_mut().device_mut::<Random>() { *random = Random::new(seed) }
+
+    let mut keys = keys.bytes();
+    let mut steps = 0;
+    while steps < max_steps && !cpu.halted() && cpu.fault().is_none() {
+        if let Some(keyboard) = cpu.bus_mut().device_mut::<Keyboard>() {
+            if keyboard.status() & STATUS_READY == 0 {
+                if let Some(key) = keys.next() { keyboard.press(key) }
+            }
+        }
+        steps += cpu.run(1);
+    }
+
+    // There's no audio here, tones are only logged
+    let tones = cpu.bus_mut().device_mut::<Sound>().map(|s| s.take_tones()).unwrap_or_default();
+    for tone in tones {
+        println!("Tone: {} Hz for {} ms at volume {}", tone.frequency, tone.duration, tone.volume);
+    }
+
+    let output = cpu.bus_mut().device_mut::<Console>().map(|c| c.take_output()).unwrap_or_default();
+    if !output.is_empty() {
+        print!("{output}");
+        if !output.ends_with('\n') { println!() }
+    }
+
+    for (r, name) in isa::REGISTER_NAMES.iter().enumerate() {
+        println!("{name}: {:04X}", cpu.reg(r as u8));
+    }
+    println!("Carry flag: {}", cpu.carry() as u8);
+    let flags = cpu.flags();
+    println!("Flags: C={} Z={} N={} V={}", flags.c as u8, flags.z as u8, flags.n as u8, flags.v as u8);
+    println!("Interrupts: {}", if cpu.interrupts_enabled() {"enabled"} else {"disabled"});
+    println!("Pointer: {:04X}", cpu.pc());
+    println!("SP: {:04X}", cpu.sp());
+    println!("Seed: {seed}");
+    match cpu.fault() {
+        Some(fault) => println!("Steps: {steps} (fault at {:04X}: {fault})", cpu.pc()),
+        None if cpu.halted() => println!("Steps: {steps} (halted)"),
+        None => println!("Steps: {steps} (step limit reached)"),
+    }
+
+    let (start, end) = dump;
+    for row in (start & !0xf..=end).step_by(16) {
+        let cells = (row..row + 16)
+            .map(|a| if a < start || a > end {"  ".to_owned()} else {format!("{:02X}", cpu.read(a))})
+            .collect::<Vec<_>>();
+        println!("{row:04X}: {}", cells.join(" ").trim_end());
+    }
+
+    if !cpu.halted() { exit(1) }
+}
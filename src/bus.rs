@@ -0,0 +1,263 @@
+use std::any::Any;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::console::Console;
+use crate::cpu::RAM_SIZE;
+use crate::framebuffer::Framebuffer;
+use crate::keyboard::Keyboard;
+use crate::panel::Panel;
+use crate::random::Random;
+use crate::sound::Sound;
+use crate::timer::Timer;
+
+/// What a program may do at an address, the CPU faults on anything else. Loading a program and editing memory in the
+/// UI aren't held to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// Read, write and execute.
+    Ram,
+    /// Read and execute, writes fault.
+    Rom,
+    /// Read and write, executing faults.
+    Data,
+    /// Every access faults.
+    NoAccess,
+}
+
+impl Region {
+    /// The name used by the `.region` directive.
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Ram => "ram",
+            Region::Rom => "rom",
+            Region::Data => "data",
+            Region::NoAccess => "noaccess",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Region::Ram, Region::Rom, Region::Data, Region::NoAccess].into_iter().find(|r| r.name() == name)
+    }
+
+    pub fn executable(self) -> bool {
+        matches!(self, Region::Ram | Region::Rom)
+    }
+}
+
+/// Something on the bus that answers to a range of addresses, such as memory or a peripheral. Addresses passed to
+/// it are full bus addresses, not offsets into the range.
+///
+/// Reads must not change the device, the UI reads every visible address each frame. Devices are found again by
+/// their type through `Bus::device`.
+pub trait Device: Any + fmt::Debug {
+    /// Shown in the UI's device list.
+    fn name(&self) -> &str;
+    fn range(&self) -> RangeInclusive<usize>;
+
+    fn read(&self, addr: usize) -> u8;
+    fn write(&mut self, addr: usize, n: u8);
+
+    /// The region its range is marked as when attached, `Bus::protect` can change it afterwards.
+    fn region(&self) -> Region { Region::Data }
+
+    /// Reads a big-endian word that lies wholly in the device, by default two byte reads.
+    fn read_word(&self, addr: usize) -> u16 {
+        (self.read(addr) as u16) << 8 | self.read(addr + 1) as u16
+    }
+
+    /// Writes a big-endian word that lies wholly in the device, by default two byte writes high byte first.
+    fn write_word(&mut self, addr: usize, n: u16) {
+        self.write(addr, (n >> 8) as u8);
+        self.write(addr + 1, n as u8);
+    }
+
+    /// Called once before every step.
+    fn tick(&mut self) {}
+
+    /// The IRQ line this device is holding up. Lines are level-triggered, the CPU only sees it pending while it's held,
+    /// so a handler has to acknowledge the device before IRET or it's entered again.
+    fn irq(&self) -> Option<u8> { None }
+
+    /// Puts the device back to how it was when the machine was switched on, called by `Cpu::reset`.
+    fn reset(&mut self) {}
+}
+
+/// A device made of big-endian word ports at even addresses, it only has to map addresses to ports and gets the rest
+/// of `Device` from that. Byte reads get half a port and a byte store to a port's first address writes it, zero
+/// extended. Word accesses at odd addresses read 0 and are ignored.
+pub trait WordPorts: Any + fmt::Debug {
+    fn name(&self) -> &str;
+    fn range(&self) -> RangeInclusive<usize>;
+
+    /// The port at the even address `addr`, 0 if there isn't one.
+    fn port(&self, addr: usize) -> u16;
+    fn store(&mut self, addr: usize, n: u16);
+
+    fn tick(&mut self) {}
+    fn irq(&self) -> Option<u8> { None }
+    fn reset(&mut self) {}
+}
+
+impl<T: WordPorts> Device for T {
+    fn name(&self) -> &str { WordPorts::name(self) }
+    fn range(&self) -> RangeInclusive<usize> { WordPorts::range(self) }
+
+    fn read(&self, addr: usize) -> u8 {
+        let n = self.port(addr & !1);
+        if addr & 1 == 0 { (n >> 8) as u8 } else { n as u8 }
+    }
+
+    fn write(&mut self, addr: usize, n: u8) {
+        if addr & 1 == 0 { self.store(addr, n as u16) }
+    }
+
+    fn read_word(&self, addr: usize) -> u16 { self.port(addr) }
+    fn write_word(&mut self, addr: usize, n: u16) { self.store(addr, n) }
+
+    fn tick(&mut self) { WordPorts::tick(self) }
+    fn irq(&self) -> Option<u8> { WordPorts::irq(self) }
+    fn reset(&mut self) { WordPorts::reset(self) }
+}
+
+/// Plain read-write memory.
+#[derive(Debug, Clone)]
+pub struct Ram {
+    start: usize,
+    bytes: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(range: RangeInclusive<usize>) -> Self {
+        Self { start: *range.start(), bytes: vec![0; range.count()] }
+    }
+}
+
+impl Device for Ram {
+    fn name(&self) -> &str { "RAM" }
+    fn range(&self) -> RangeInclusive<usize> { self.start..=self.start + self.bytes.len() - 1 }
+    fn read(&self, addr: usize) -> u8 { self.bytes[addr - self.start] }
+    fn write(&mut self, addr: usize, n: u8) { self.bytes[addr - self.start] = n }
+    fn region(&self) -> Region { Region::Ram }
+}
+
+/// Routes every address to the device attached over it.
+pub struct Bus {
+    devices: Vec<Box<dyn Device>>,
+    /// Index into `devices` for every address.
+    map: Vec<u8>,
+    regions: Vec<Region>,
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.devices.iter().map(|d| (d.name(), d.range()))).finish()
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Bus {
+    /// A bus with RAM at every address and nothing else.
+    pub fn new() -> Self {
+        Self {
+            devices: vec![Box::new(Ram::new(0..=RAM_SIZE - 1))],
+            map: vec![0; RAM_SIZE],
+            regions: vec![Region::Ram; RAM_SIZE],
+        }
+    }
+
+    /// The machine the visualizer runs: RAM with the framebuffer and every peripheral attached over it.
+    pub fn standard() -> Self {
+        let mut bus = Self::new();
+        bus.attach(Framebuffer::new());
+        bus.attach(Console::new());
+        bus.attach(Keyboard::new());
+        bus.attach(Timer::new());
+        bus.attach(Panel::new());
+        bus.attach(Random::default());
+        bus.attach(Sound::new());
+        bus
+    }
+
+    /// Adds a device, it takes over its range from whatever was attached there before along with the device's region.
+    pub fn attach(&mut self, device: impl Device) {
+        assert!(self.devices.len() < 0x100, "too many devices on the bus");
+        let range = device.range();
+        assert!(*range.end() < RAM_SIZE, "{} lies past the end of the address space", device.name());
+
+        self.map[range.clone()].fill(self.devices.len() as u8);
+        self.regions[range].fill(device.region());
+        self.devices.push(Box::new(device));
+    }
+
+    /// Marks `range` as `region`, whatever is attached there.
+    pub fn protect(&mut self, range: RangeInclusive<usize>, region: Region) {
+        self.regions[range].fill(region);
+    }
+
+    pub fn region(&self, addr: usize) -> Region {
+        self.regions[addr]
+    }
+
+    /// Every attached device, in the order they were attached.
+    pub fn devices(&self) -> impl Iterator<Item = &dyn Device> {
+        self.devices.iter().map(|d| d.as_ref())
+    }
+
+    /// The first attached device of type `T`.
+    pub fn device<T: Device>(&self) -> Option<&T> {
+        self.devices.iter().find_map(|d| (d.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    pub fn device_mut<T: Device>(&mut self) -> Option<&mut T> {
+        self.devices.iter_mut().find_map(|d| (d.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// The device answering at `addr`.
+    pub fn device_at(&self, addr: usize) -> &dyn Device {
+        self.devices[self.map[addr] as usize].as_ref()
+    }
+
+    pub fn read(&self, addr: usize) -> u8 {
+        self.device_at(addr).read(addr)
+    }
+
+    pub fn write(&mut self, addr: usize, n: u8) {
+        self.devices[self.map[addr] as usize].write(addr, n)
+    }
+
+    /// Reads a big-endian word, `addr + 1` must be on the bus. A word across two devices is read a byte from each.
+    pub fn read_word(&self, addr: usize) -> u16 {
+        if self.map[addr] == self.map[addr + 1] { self.device_at(addr).read_word(addr) }
+        else { (self.read(addr) as u16) << 8 | self.read(addr + 1) as u16 }
+    }
+
+    pub fn write_word(&mut self, addr: usize, n: u16) {
+        if self.map[addr] == self.map[addr + 1] { self.devices[self.map[addr] as usize].write_word(addr, n) }
+        else {
+            self.write(addr, (n >> 8) as u8);
+            self.write(addr + 1, n as u8);
+        }
+    }
+
+    /// Ticks every device, returns the IRQ lines they're holding up with bit 0 for IRQ 0.
+    pub fn tick(&mut self) -> u8 {
+        let mut irqs = 0;
+        for device in &mut self.devices {
+            device.tick();
+            if let Some(irq) = device.irq() { irqs |= 1 << irq }
+        }
+        irqs
+    }
+
+    pub fn reset(&mut self) {
+        for device in &mut self.devices {
+            device.reset();
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::PORTS;
+
+/// Word port, storing a character prints it at the cursor. `\n` starts a new line, `\r` returns to the start of the
+/// line and 08 deletes the character before the cursor.
+pub const CONSOLE_OUT: usize = PORTS;
+/// Word port, storing 1 clears the screen and moves the cursor home.
+pub const CONSOLE_CONTROL: usize = PORTS + 0x02;
+/// Word port holding the cursor as `row * COLUMNS + column`, it can be read and moved.
+pub const CONSOLE_CURSOR: usize = PORTS + 0x04;
+/// Word port, storing a value prints it in decimal.
+pub const CONSOLE_NUMBER: usize = PORTS + 0x06;
+
+pub const COLUMNS: usize = 40;
+pub const ROWS: usize = 7;
+
+/// The text console behind the console ports, it scrolls up once the cursor runs off the last row.
+#[derive(Debug, Clone)]
+pub struct Console {
+    cells: Vec<u8>,
+    cursor: usize,
+    output: String,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            cells: vec![b' '; COLUMNS * ROWS],
+            cursor: 0,
+            output: String::new(),
+        }
+    }
+
+    pub fn cursor(&self) -> usize { self.cursor }
+
+    /// The characters on screen, one row at a time.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.cells.chunks(COLUMNS)
+    }
+
+    /// Everything printed since the last call, for mirroring the console to stdout.
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(b' ');
+        self.cursor = 0;
+    }
+
+    fn put(&mut self, c: u8) {
+        match c {
+            b'\n' => self.cursor = (self.cursor / COLUMNS + 1) * COLUMNS,
+            b'\r' => self.cursor -= self.cursor % COLUMNS,
+            0x08 => if self.cursor > 0 {
+                self.cursor -= 1;
+                self.cells[self.cursor] = b' ';
+            },
+            c if c.is_ascii_graphic() || c == b' ' => {
+                self.cells[self.cursor] = c;
+                self.cursor += 1;
+            }
+            _ => return,
+        }
+        self.output.push(c as char);
+
+        if self.cursor >= self.cells.len() {
+            self.cells.copy_within(COLUMNS.., 0);
+            let last = self.cells.len() - COLUMNS;
+            self.cells[last..].fill(b' ');
+            self.cursor -= COLUMNS;
+        }
+    }
+}
+
+impl WordPorts for Console {
+    fn name(&self) -> &str { "Console" }
+    fn range(&self) -> RangeInclusive<usize> { CONSOLE_OUT..=CONSOLE_NUMBER + 1 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            CONSOLE_CURSOR => self.cursor as u16,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, addr: usize, n: u16) {
+        match addr {
+            CONSOLE_OUT => self.put(n as u8),
+            CONSOLE_CONTROL if n == 1 => self.clear(),
+            CONSOLE_CURSOR => self.cursor = n as usize % self.cells.len(),
+            CONSOLE_NUMBER => for c in n.to_string().bytes() { self.put(c) },
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) { self.clear() }
+}
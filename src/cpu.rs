@@ -0,0 +1,353 @@
+use std::fmt;
+
+use crate::bus::{Bus, Region};
+use crate::isa;
+
+pub const RAM_SIZE: usize = 0x1_0000;
+
+/// Size of the register file, R0 and R1 are also called X and Y.
+pub const REGISTERS: usize = 8;
+
+/// Where the stack pointer starts, the stack grows down from here.
+pub const STACK_TOP: u16 = 0xfff0;
+
+/// The lowest address the stack may grow down to, it keeps to the top half of the FF00 page so a runaway
+/// recursion faults before it reaches a program's data below or the device ports.
+pub const STACK_LIMIT: u16 = 0xff80;
+
+/// The interrupt vector table above the stack, one big-endian handler address for each of the 8 IRQ lines. A
+/// vector of 0000 means no handler and the interrupt is dropped.
+pub const VECTORS: usize = 0xfff0;
+
+pub const IRQ_TIMER: u8 = 0;
+pub const IRQ_KEYBOARD: u8 = 1;
+
+/// Start of the 256 bytes the standard devices' ports are in, see the `console`, `keyboard`, `timer`, `panel`,
+/// `random` and `sound` modules for what's there.
+pub const PORTS: usize = 0xfe00;
+
+/// A program error that stops the CPU, it stays on the faulting instruction until resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    IllegalOpcode(u8),
+    /// A register operand byte that doesn't name one of the `REGISTERS`.
+    IllegalRegister(u8),
+    DivideByZero,
+    /// A push would move the stack pointer below `STACK_LIMIT`.
+    StackOverflow,
+    /// A pop with the stack pointer already at `STACK_TOP`.
+    StackUnderflow,
+    /// An access or instruction fetch ran past the end of memory, holds the first address it couldn't reach.
+    BusOverflow(usize),
+    /// A store to a `Region::Rom` address.
+    WriteToRom(usize),
+    /// An instruction fetched from a `Region::Data` address.
+    NotExecutable(usize),
+    /// Any access to a `Region::NoAccess` address.
+    NoAccess(usize),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::IllegalOpcode(op) => write!(f, "illegal opcode {op:02X}"),
+            Fault::IllegalRegister(r) => write!(f, "illegal register {r:02X}"),
+            Fault::DivideByZero => write!(f, "divide by zero"),
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "stack underflow"),
+            Fault::BusOverflow(addr) => write!(f, "bus overflow at {addr:04X}"),
+            Fault::WriteToRom(addr) => write!(f, "write to ROM at {addr:04X}"),
+            Fault::NotExecutable(addr) => write!(f, "executing data at {addr:04X}"),
+            Fault::NoAccess(addr) => write!(f, "no access at {addr:04X}"),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// The status register, written by the ALU instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags {
+    /// Carry, the unsigned result didn't fit in 16 bits. Subtraction sets it when it borrows.
+    pub c: bool,
+    /// Zero, the result was 0.
+    pub z: bool,
+    /// Negative, bit 15 of the result was set.
+    pub n: bool,
+    /// Overflow, the signed result didn't fit in 16 bits.
+    pub v: bool,
+}
+
+impl Flags {
+    /// Flags for an ALU result, Z and N are worked out from `n`.
+    pub fn from_result(n: u16, c: bool, v: bool) -> Self {
+        Self { c, z: n == 0, n: n & 0x8000 != 0, v }
+    }
+
+    /// Packs the flags into a word for the stack, C is bit 0 followed by Z, N and V.
+    pub fn bits(self) -> u16 {
+        self.c as u16 | (self.z as u16) << 1 | (self.n as u16) << 2 | (self.v as u16) << 3
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        Self { c: bits & 1 != 0, z: bits & 2 != 0, n: bits & 4 != 0, v: bits & 8 != 0 }
+    }
+}
+
+/// The emulated machine: the bus with memory and devices on it, the register file, the status flags, the stack
+/// pointer, the program counter and the interrupt controller.
+#[derive(Debug)]
+pub struct Cpu {
+    bus: Bus,
+    pc: usize,
+    sp: u16,
+    regs: [u16; REGISTERS],
+    flags: Flags,
+    halted: bool,
+    fault: Option<Fault>,
+    touched: Option<usize>,
+    /// Interrupt enable, cleared on entry to a handler and set again by IRET.
+    ie: bool,
+    /// One bit per IRQ line raised with `raise` and not yet serviced.
+    raised: u8,
+    /// The IRQ lines waiting this step, `raised` plus the lines the devices are holding up.
+    pending: u8,
+    interrupted: Option<u8>,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cpu {
+    /// A CPU on the standard bus.
+    pub fn new() -> Self {
+        Self::with_bus(Bus::standard())
+    }
+
+    pub fn with_bus(bus: Bus) -> Self {
+        Self {
+            bus,
+            pc: 0,
+            sp: STACK_TOP,
+            regs: [0; REGISTERS],
+            flags: Flags::default(),
+            halted: false,
+            fault: None,
+            touched: None,
+            ie: false,
+            raised: 0,
+            pending: 0,
+            interrupted: None,
+        }
+    }
+
+    /// Copies `bytes` onto the bus starting at `addr`, like `write` they go straight to whatever is there.
+    pub fn load(&mut self, addr: usize, bytes: &[u8]) {
+        for (i, &n) in bytes.iter().enumerate() {
+            self.bus.write(addr + i, n);
+        }
+    }
+
+    /// Clears the registers, flags, program counter and interrupts and resets every device, see `Device::reset`.
+    /// Memory is left as it is.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.sp = STACK_TOP;
+        self.regs = [0; REGISTERS];
+        self.flags = Flags::default();
+        self.halted = false;
+        self.fault = None;
+        self.touched = None;
+        self.ie = false;
+        self.raised = 0;
+        self.pending = 0;
+        self.interrupted = None;
+        self.bus.reset();
+    }
+
+    pub fn pc(&self) -> usize { self.pc }
+    pub fn sp(&self) -> u16 { self.sp }
+    pub fn x(&self) -> u16 { self.regs[0] }
+    pub fn y(&self) -> u16 { self.regs[1] }
+    pub fn flags(&self) -> Flags { self.flags }
+    pub fn carry(&self) -> bool { self.flags.c }
+    pub fn halted(&self) -> bool { self.halted }
+    /// The fault that stopped the CPU, the program counter still points at the faulting instruction.
+    pub fn fault(&self) -> Option<Fault> { self.fault }
+    /// The cell read or written by the last single byte load or store, if the last step did one.
+    pub fn touched(&self) -> Option<usize> { self.touched }
+    pub fn interrupts_enabled(&self) -> bool { self.ie }
+    /// IRQ lines waiting to be serviced, bit 0 is IRQ 0.
+    pub fn pending(&self) -> u8 { self.pending }
+    /// The IRQ whose handler the last step jumped to, if it did.
+    pub fn interrupted(&self) -> Option<u8> { self.interrupted }
+    pub fn bus(&self) -> &Bus { &self.bus }
+    /// The bus, for reaching devices with `Bus::device_mut`.
+    pub fn bus_mut(&mut self) -> &mut Bus { &mut self.bus }
+
+    /// Moves the program counter, this also resumes the CPU if it was halted or faulted.
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+        self.resume();
+    }
+
+    /// Clears a halt or fault so that `step` runs the current instruction again.
+    pub fn resume(&mut self) {
+        self.halted = false;
+        self.fault = None;
+    }
+
+    pub fn set_sp(&mut self, sp: u16) { self.sp = sp }
+    pub fn set_x(&mut self, n: u16) { self.regs[0] = n }
+    pub fn set_y(&mut self, n: u16) { self.regs[1] = n }
+    pub fn set_flags(&mut self, flags: Flags) { self.flags = flags }
+    pub fn set_carry(&mut self, c: bool) { self.flags.c = c }
+    pub fn set_interrupts_enabled(&mut self, ie: bool) { self.ie = ie }
+
+    /// Raises an IRQ line, it stays pending until its handler is entered.
+    pub fn raise(&mut self, irq: u8) {
+        self.raised |= 1 << irq;
+        self.pending |= 1 << irq;
+    }
+
+    pub fn read(&self, addr: usize) -> u8 { self.bus.read(addr) }
+    /// Writes the bus directly, this ignores the region so it can patch ROM.
+    pub fn write(&mut self, addr: usize, n: u8) { self.bus.write(addr, n) }
+
+    /// Faults unless a program may read `addr`, or store to it if `store` is set.
+    fn check(&self, addr: usize, store: bool) -> Result<(), Fault> {
+        match self.bus.region(addr) {
+            Region::NoAccess => Err(Fault::NoAccess(addr)),
+            Region::Rom if store => Err(Fault::WriteToRom(addr)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads a byte of an instruction, unlike `read` this faults unless the region is executable.
+    pub fn fetch(&self, addr: usize) -> Result<u8, Fault> {
+        self.check(addr, false)?;
+        if !self.bus.region(addr).executable() { return Err(Fault::NotExecutable(addr)) }
+        Ok(self.bus.read(addr))
+    }
+
+    /// Reads a byte for a program, unlike `read` this checks the region and is remembered in `touched`.
+    pub fn load_byte(&mut self, addr: usize) -> Result<u8, Fault> {
+        self.check(addr, false)?;
+        self.touched = Some(addr);
+        Ok(self.bus.read(addr))
+    }
+
+    /// Writes a byte for a program, unlike `write` this checks the region and is remembered in `touched`.
+    pub fn store_byte(&mut self, addr: usize, n: u8) -> Result<(), Fault> {
+        self.check(addr, true)?;
+        self.touched = Some(addr);
+        self.bus.write(addr, n);
+        Ok(())
+    }
+
+    /// Reads a big-endian word, fails if its second byte would be past 0xFFFF or either byte can't be read.
+    pub fn read_word(&self, addr: usize) -> Result<u16, Fault> {
+        if addr >= RAM_SIZE - 1 { return Err(Fault::BusOverflow(addr.max(RAM_SIZE))) }
+        self.check(addr, false)?;
+        self.check(addr + 1, false)?;
+        Ok(self.bus.read_word(addr))
+    }
+
+    /// Writes a big-endian word, fails if its second byte would be past 0xFFFF or either byte can't be written.
+    pub fn write_word(&mut self, addr: usize, n: u16) -> Result<(), Fault> {
+        if addr >= RAM_SIZE - 1 { return Err(Fault::BusOverflow(addr.max(RAM_SIZE))) }
+        self.check(addr, true)?;
+        self.check(addr + 1, true)?;
+        self.bus.write_word(addr, n);
+        Ok(())
+    }
+
+    /// Reads a register by its operand encoding, panics unless `r` is below `REGISTERS`. Decoding an instruction
+    /// already faults on bad register bytes.
+    pub fn reg(&self, r: u8) -> u16 { self.regs[r as usize] }
+    pub fn set_reg(&mut self, r: u8, n: u16) { self.regs[r as usize] = n }
+
+    /// Moves the stack pointer down by 2 and writes `n` there.
+    pub fn push(&mut self, n: u16) -> Result<(), Fault> {
+        if self.sp < STACK_LIMIT + 2 { return Err(Fault::StackOverflow) }
+        self.write_word(self.sp as usize - 2, n)?;
+        self.sp -= 2;
+        Ok(())
+    }
+
+    /// Reads the word at the stack pointer and moves it up by 2.
+    pub fn pop(&mut self) -> Result<u16, Fault> {
+        if self.sp >= STACK_TOP { return Err(Fault::StackUnderflow) }
+        let n = self.read_word(self.sp as usize)?;
+        self.sp += 2;
+        Ok(n)
+    }
+
+    /// Stops the CPU until the program counter is moved with `set_pc`.
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    /// Executes the instruction at the program counter, or jumps to the handler of the lowest pending IRQ if
+    /// interrupts are enabled. A fault leaves the program counter on the instruction and keeps the CPU stopped until
+    /// `resume` or `set_pc` is called.
+    pub fn step(&mut self) -> Result<(), Fault> {
+        if let Some(fault) = self.fault { return Err(fault) }
+        if self.halted { return Ok(()) }
+
+        self.touched = None;
+        self.interrupted = None;
+        self.tick();
+
+        let result = match self.pending.trailing_zeros() {
+            irq if self.ie && irq < 8 => self.interrupt(irq as u8),
+            _ => self.execute(),
+        };
+        if let Err(fault) = result { self.fault = Some(fault) }
+        result
+    }
+
+    /// Device lines are level-triggered, they're only pending for as long as the device holds them up.
+    fn tick(&mut self) {
+        self.pending = self.raised | self.bus.tick();
+    }
+
+    /// Pushes the program counter and then the flags, disables interrupts and jumps to the IRQ's vector.
+    fn interrupt(&mut self, irq: u8) -> Result<(), Fault> {
+        self.raised &= !(1 << irq);
+        self.pending &= !(1 << irq);
+        let vector = self.read_word(VECTORS + irq as usize * 2)?;
+        if vector == 0 { return self.execute() }
+
+        self.push(self.pc as u16)?;
+        self.push(self.flags.bits())?;
+        self.ie = false;
+        self.pc = vector as usize;
+        self.interrupted = Some(irq);
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<(), Fault> {
+        if self.pc >= RAM_SIZE { return Err(Fault::BusOverflow(self.pc)) }
+
+        let opcode = self.fetch(self.pc)?;
+        let inst = isa::by_opcode(opcode).ok_or(Fault::IllegalOpcode(opcode))?;
+        let args = inst.decode(self, self.pc)?;
+        self.pc = (inst.exec)(self, &args)?.unwrap_or((self.pc + inst.size()) % RAM_SIZE);
+        Ok(())
+    }
+
+    /// Steps until the CPU halts, faults or `max_steps` instructions have been executed, returns the number of
+    /// instructions that completed.
+    pub fn run(&mut self, max_steps: usize) -> usize {
+        let mut steps = 0;
+        while steps < max_steps && !self.halted {
+            if self.step().is_err() {break}
+            steps += 1;
+        }
+        steps
+    }
+}
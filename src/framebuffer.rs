@@ -0,0 +1,44 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::Device;
+
+/// Start of the framebuffer, 4 pixels per byte with the leftmost pixel in the top two bits, 32 bytes per row.
+pub const FRAMEBUFFER: usize = 0xe000;
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 128;
+/// Framebuffer size in bytes, it ends at 0xEFFF.
+pub const SIZE: usize = WIDTH * HEIGHT / 4;
+
+/// The 4 colours a pixel can be, as 0xRRGGBB.
+pub const PALETTE: [u32; 4] = [0x000000, 0x87ceeb, 0xff8c00, 0xffffff];
+
+/// Video memory, read and written like RAM and drawn by the UI.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    bytes: Vec<u8>,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self { bytes: vec![0; SIZE] }
+    }
+
+    /// The colour index of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        let i = y * WIDTH + x;
+        self.bytes[i / 4] >> (6 - i % 4 * 2) & 0b11
+    }
+}
+
+impl Device for Framebuffer {
+    fn name(&self) -> &str { "Framebuffer" }
+    fn range(&self) -> RangeInclusive<usize> { FRAMEBUFFER..=FRAMEBUFFER + SIZE - 1 }
+    fn read(&self, addr: usize) -> u8 { self.bytes[addr - FRAMEBUFFER] }
+    fn write(&mut self, addr: usize, n: u8) { self.bytes[addr - FRAMEBUFFER] = n }
+}
@@ -0,0 +1,485 @@
+use crate::cpu::{Cpu, Fault, Flags, RAM_SIZE, REGISTERS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// One byte selecting a register, 0 to 7 with X and Y being R0 and R1.
+    Register,
+    /// A 16-bit immediate value.
+    Value,
+    /// A 16-bit memory address.
+    Pointer,
+    /// A register byte followed by a 16-bit offset, written `[Y]`, `[X+4]` or `[addr+Y]`. Decodes to the address
+    /// the register plus the offset points at.
+    Indexed,
+}
+
+impl Operand {
+    pub fn size(self) -> usize {
+        match self {
+            Operand::Register => 1,
+            Operand::Value | Operand::Pointer => 2,
+            Operand::Indexed => 3,
+        }
+    }
+}
+
+/// How an instruction affects the program counter, used to follow the code when labeling bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// Jumps to its pointer operand only if a condition holds.
+    Branch,
+    Jump,
+    /// Jumps to an address taken off the stack.
+    Return,
+    /// Jumps to an address worked out from a register, so it can't be followed without running it.
+    Indirect,
+    Halt,
+}
+
+/// How registers are written in the source, R0 and R1 may also be written by number.
+pub const REGISTER_NAMES: [&str; REGISTERS] = ["X", "Y", "R2", "R3", "R4", "R5", "R6", "R7"];
+
+pub fn register_name(r: u8) -> Option<&'static str> {
+    REGISTER_NAMES.get(r as usize).copied()
+}
+
+pub struct Instruction {
+    pub mnemonic: &'static str,
+    pub opcode: u8,
+    pub operands: &'static [Operand],
+    pub flow: Flow,
+    pub description: &'static str,
+    /// Runs the instruction with its decoded operands, returns the new program counter if it jumps.
+    pub exec: fn(&mut Cpu, &[u16]) -> Result<Option<usize>, Fault>,
+}
+
+impl Instruction {
+    /// Size in bytes including the opcode.
+    pub fn size(&self) -> usize {
+        1 + self.operands.iter().map(|o| o.size()).sum::<usize>()
+    }
+
+    /// Reads the operands that follow the opcode at `addr`, every byte goes through `Cpu::fetch`.
+    pub fn decode(&self, cpu: &Cpu, addr: usize) -> Result<Vec<u16>, Fault> {
+        if addr + self.size() > RAM_SIZE { return Err(Fault::BusOverflow(addr.max(RAM_SIZE))) }
+
+        let register = |at: usize| match cpu.fetch(at)? {
+            r if (r as usize) < REGISTERS => Ok(r),
+            r => Err(Fault::IllegalRegister(r)),
+        };
+        let word = |at: usize| Ok::<_, Fault>(u16::from_be_bytes([cpu.fetch(at)?, cpu.fetch(at + 1)?]));
+
+        let mut at = addr + 1;
+        self.operands.iter().map(|o| {
+            let n = match o {
+                Operand::Register => register(at)? as u16,
+                Operand::Value | Operand::Pointer => word(at)?,
+                Operand::Indexed => cpu.reg(register(at)?).wrapping_add(word(at + 1)?),
+            };
+            at += o.size();
+            Ok(n)
+        }).collect()
+    }
+}
+
+/// Writes an ALU result to X and sets the flags from it.
+fn alu(cpu: &mut Cpu, n: u16, c: bool, v: bool) -> Result<Option<usize>, Fault> {
+    cpu.set_x(n);
+    cpu.set_flags(Flags::from_result(n, c, v));
+    Ok(None)
+}
+
+use Operand::*;
+
+pub static INSTRUCTIONS: &[Instruction] = &[
+    Instruction {
+        mnemonic: "NOP", opcode: 0x00, operands: &[], flow: Flow::Next,
+        description: "No operation, just increments the program counter, usually used for padding.",
+        exec: |_, _| Ok(None),
+    },
+    Instruction {
+        mnemonic: "HLT", opcode: 0x01, operands: &[], flow: Flow::Halt,
+        description: "Halt, halts the program.",
+        exec: |cpu, _| { cpu.halt(); Ok(Some(cpu.pc())) },
+    },
+
+    Instruction {
+        mnemonic: "MOV", opcode: 0x10, operands: &[Register], flow: Flow::Next,
+        description: "Move, copies X into a register, MOV X copies Y into X instead.",
+        exec: |cpu, a| {
+            if a[0] == 0x00 { cpu.set_x(cpu.y()) } else { cpu.set_reg(a[0] as u8, cpu.x()) }
+            Ok(None)
+        },
+    },
+    Instruction {
+        mnemonic: "LOD", opcode: 0x11, operands: &[Register, Pointer], flow: Flow::Next,
+        description: "Load, loads value from memory to a register.",
+        exec: |cpu, a| { cpu.set_reg(a[0] as u8, cpu.read_word(a[1] as usize)?); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STO", opcode: 0x12, operands: &[Register, Pointer], flow: Flow::Next,
+        description: "Store, stores value from a register to memory.",
+        exec: |cpu, a| { cpu.write_word(a[1] as usize, cpu.reg(a[0] as u8))?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "LDR", opcode: 0x13, operands: &[Register], flow: Flow::Next,
+        description: "Load by register, loads value from memory to a register using X as an address.",
+        exec: |cpu, a| { cpu.set_reg(a[0] as u8, cpu.read_word(cpu.x() as usize)?); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STR", opcode: 0x14, operands: &[Register], flow: Flow::Next,
+        description: "Store by register, stores value from a register to memory using X as an address.",
+        exec: |cpu, a| { cpu.write_word(cpu.x() as usize, cpu.reg(a[0] as u8))?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "SWP", opcode: 0x15, operands: &[], flow: Flow::Next,
+        description: "Swap, swaps X and Y.",
+        exec: |cpu, _| {
+            let (x, y) = (cpu.x(), cpu.y());
+            cpu.set_x(y);
+            cpu.set_y(x);
+            Ok(None)
+        },
+    },
+    Instruction {
+        mnemonic: "LDI", opcode: 0x16, operands: &[Register, Value], flow: Flow::Next,
+        description: "Load immediate, loads value into a register.",
+        exec: |cpu, a| { cpu.set_reg(a[0] as u8, a[1]); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "LDB", opcode: 0x17, operands: &[Register, Pointer], flow: Flow::Next,
+        description: "Load byte, loads one byte from memory into a register, the high half is cleared.",
+        exec: |cpu, a| { let n = cpu.load_byte(a[1] as usize)?; cpu.set_reg(a[0] as u8, n as u16); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STB", opcode: 0x18, operands: &[Register, Pointer], flow: Flow::Next,
+        description: "Store byte, stores the low half of a register to one memory cell.",
+        exec: |cpu, a| { cpu.store_byte(a[1] as usize, cpu.reg(a[0] as u8) as u8)?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "LDRB", opcode: 0x19, operands: &[Register], flow: Flow::Next,
+        description: "Load byte by register, loads one byte into a register using X as an address.",
+        exec: |cpu, a| { let n = cpu.load_byte(cpu.x() as usize)?; cpu.set_reg(a[0] as u8, n as u16); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STRB", opcode: 0x1a, operands: &[Register], flow: Flow::Next,
+        description: "Store byte by register, stores the low half of a register using X as an address.",
+        exec: |cpu, a| { cpu.store_byte(cpu.x() as usize, cpu.reg(a[0] as u8) as u8)?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "LOD", opcode: 0x1b, operands: &[Register, Indexed], flow: Flow::Next,
+        description: "Load indexed, loads a register from the address in a register plus an offset.",
+        exec: |cpu, a| { let n = cpu.read_word(a[1] as usize)?; cpu.set_reg(a[0] as u8, n); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STO", opcode: 0x1c, operands: &[Register, Indexed], flow: Flow::Next,
+        description: "Store indexed, stores a register to the address in a register plus an offset.",
+        exec: |cpu, a| { cpu.write_word(a[1] as usize, cpu.reg(a[0] as u8))?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "LDB", opcode: 0x1d, operands: &[Register, Indexed], flow: Flow::Next,
+        description: "Load byte indexed, like LDB but the address is a register plus an offset.",
+        exec: |cpu, a| { let n = cpu.load_byte(a[1] as usize)?; cpu.set_reg(a[0] as u8, n as u16); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "STB", opcode: 0x1e, operands: &[Register, Indexed], flow: Flow::Next,
+        description: "Store byte indexed, like STB but the address is a register plus an offset.",
+        exec: |cpu, a| { cpu.store_byte(a[1] as usize, cpu.reg(a[0] as u8) as u8)?; Ok(None) },
+    },
+
+    Instruction {
+        mnemonic: "ADD", opcode: 0x20, operands: &[], flow: Flow::Next,
+        description: "Add, adds the Y value to X and sets the flags.",
+        exec: |cpu, _| {
+            let (x, y) = (cpu.x(), cpu.y());
+            let (n, c) = x.overflowing_add(y);
+            let v = (x as i16).overflowing_add(y as i16).1;
+            alu(cpu, n, c, v)
+        },
+    },
+    Instruction {
+        mnemonic: "SUB", opcode: 0x21, operands: &[], flow: Flow::Next,
+        description: "Subtract, subtracts the Y value from X and sets the flags, carry means it borrowed.",
+        exec: |cpu, _| {
+            let (x, y) = (cpu.x(), cpu.y());
+            let (n, c) = x.overflowing_sub(y);
+            let v = (x as i16).overflowing_sub(y as i16).1;
+            alu(cpu, n, c, v)
+        },
+    },
+    Instruction {
+        mnemonic: "MUL", opcode: 0x22, operands: &[], flow: Flow::Next,
+        description: "Multiply, multiplies X by Y and sets the flags.",
+        exec: |cpu, _| {
+            let (x, y) = (cpu.x(), cpu.y());
+            let n = x.wrapping_mul(y);
+            let c = x as u32 * y as u32 > u16::MAX as u32;
+            let v = (x as i16).overflowing_mul(y as i16).1;
+            alu(cpu, n, c, v)
+        },
+    },
+    Instruction {
+        mnemonic: "DIV", opcode: 0x23, operands: &[], flow: Flow::Next,
+        description: "Divide, divides X by Y and sets the flags.",
+        exec: |cpu, _| {
+            if cpu.y() == 0 { return Err(Fault::DivideByZero) }
+            alu(cpu, cpu.x() / cpu.y(), false, false)
+        },
+    },
+    Instruction {
+        mnemonic: "MOD", opcode: 0x24, operands: &[], flow: Flow::Next,
+        description: "Modulo, divides X by Y and returns the remainder, sets the flags.",
+        exec: |cpu, _| {
+            if cpu.y() == 0 { return Err(Fault::DivideByZero) }
+            alu(cpu, cpu.x() % cpu.y(), false, false)
+        },
+    },
+
+    Instruction {
+        mnemonic: "AND", opcode: 0x25, operands: &[], flow: Flow::Next,
+        description: "And, keeps only the bits of X that are also set in Y.",
+        exec: |cpu, _| alu(cpu, cpu.x() & cpu.y(), false, false),
+    },
+    Instruction {
+        mnemonic: "OR", opcode: 0x26, operands: &[], flow: Flow::Next,
+        description: "Or, sets the bits of X that are set in Y.",
+        exec: |cpu, _| alu(cpu, cpu.x() | cpu.y(), false, false),
+    },
+    Instruction {
+        mnemonic: "XOR", opcode: 0x27, operands: &[], flow: Flow::Next,
+        description: "Exclusive or, flips the bits of X that are set in Y.",
+        exec: |cpu, _| alu(cpu, cpu.x() ^ cpu.y(), false, false),
+    },
+    Instruction {
+        mnemonic: "NOT", opcode: 0x28, operands: &[], flow: Flow::Next,
+        description: "Not, flips every bit of X.",
+        exec: |cpu, _| alu(cpu, !cpu.x(), false, false),
+    },
+    Instruction {
+        mnemonic: "SHL", opcode: 0x29, operands: &[], flow: Flow::Next,
+        description: "Shift left, moves the bits of X one place left, the top bit goes into the carry flag.",
+        exec: |cpu, _| {
+            let x = cpu.x();
+            alu(cpu, x << 1, x & 0x8000 != 0, (x ^ x << 1) & 0x8000 != 0)
+        },
+    },
+    Instruction {
+        mnemonic: "SHR", opcode: 0x2a, operands: &[], flow: Flow::Next,
+        description: "Shift right, moves the bits of X one place right, the bottom bit goes into the carry flag.",
+        exec: |cpu, _| alu(cpu, cpu.x() >> 1, cpu.x() & 1 != 0, false),
+    },
+    Instruction {
+        mnemonic: "ROL", opcode: 0x2b, operands: &[], flow: Flow::Next,
+        description: "Rotate left, shifts X left through the carry flag, the old carry becomes the bottom bit.",
+        exec: |cpu, _| {
+            let x = cpu.x();
+            alu(cpu, x << 1 | cpu.carry() as u16, x & 0x8000 != 0, false)
+        },
+    },
+    Instruction {
+        mnemonic: "ROR", opcode: 0x2c, operands: &[], flow: Flow::Next,
+        description: "Rotate right, shifts X right through the carry flag, the old carry becomes the top bit.",
+        exec: |cpu, _| {
+            let x = cpu.x();
+            alu(cpu, x >> 1 | (cpu.carry() as u16) << 15, x & 1 != 0, false)
+        },
+    },
+    Instruction {
+        mnemonic: "INC", opcode: 0x2d, operands: &[Register], flow: Flow::Next,
+        description: "Increment, adds 1 to a register, the carry flag is left as it is.",
+        exec: |cpu, a| {
+            let r = cpu.reg(a[0] as u8);
+            let n = r.wrapping_add(1);
+            cpu.set_reg(a[0] as u8, n);
+            cpu.set_flags(Flags::from_result(n, cpu.carry(), r == 0x7fff));
+            Ok(None)
+        },
+    },
+    Instruction {
+        mnemonic: "DEC", opcode: 0x2e, operands: &[Register], flow: Flow::Next,
+        description: "Decrement, subtracts 1 from a register, the carry flag is left as it is.",
+        exec: |cpu, a| {
+            let r = cpu.reg(a[0] as u8);
+            let n = r.wrapping_sub(1);
+            cpu.set_reg(a[0] as u8, n);
+            cpu.set_flags(Flags::from_result(n, cpu.carry(), r == 0x8000));
+            Ok(None)
+        },
+    },
+    Instruction {
+        mnemonic: "CMP", opcode: 0x2f, operands: &[], flow: Flow::Next,
+        description: "Compare, subtracts Y from X and sets the flags like SUB but leaves X unchanged.",
+        exec: |cpu, _| {
+            let (x, y) = (cpu.x(), cpu.y());
+            let (n, c) = x.overflowing_sub(y);
+            let v = (x as i16).overflowing_sub(y as i16).1;
+            cpu.set_flags(Flags::from_result(n, c, v));
+            Ok(None)
+        },
+    },
+
+    Instruction {
+        mnemonic: "JMP", opcode: 0x30, operands: &[Pointer], flow: Flow::Jump,
+        description: "Jump, jumps to a memory address.",
+        exec: |_, a| Ok(Some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JZ", opcode: 0x31, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if zero, jumps to a memory address if X is zero.",
+        exec: |cpu, a| Ok((cpu.x() == 0).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JNZ", opcode: 0x32, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if not zero, jumps to a memory address if X is not zero.",
+        exec: |cpu, a| Ok((cpu.x() != 0).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JC", opcode: 0x33, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if carry, jumps to a memory address if carry flag is set.",
+        exec: |cpu, a| Ok(cpu.carry().then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JNC", opcode: 0x34, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if not carry, jumps to a memory address if carry flag is not set.",
+        exec: |cpu, a| Ok((!cpu.carry()).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JGE", opcode: 0x35, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if greater or equal, jumps to a memory address if X is greater or equal to Y.",
+        exec: |cpu, a| Ok((cpu.x() >= cpu.y()).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JL", opcode: 0x36, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if less, jumps to a memory address if X is less than Y.",
+        exec: |cpu, a| Ok((cpu.x() < cpu.y()).then_some(a[0] as usize)),
+    },
+
+    Instruction {
+        mnemonic: "JN", opcode: 0x37, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if negative, jumps to a memory address if the negative flag is set.",
+        exec: |cpu, a| Ok(cpu.flags().n.then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JNN", opcode: 0x38, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if not negative, jumps to a memory address if the negative flag is not set.",
+        exec: |cpu, a| Ok((!cpu.flags().n).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JV", opcode: 0x39, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if overflow, jumps to a memory address if the overflow flag is set.",
+        exec: |cpu, a| Ok(cpu.flags().v.then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JNV", opcode: 0x3a, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if not overflow, jumps to a memory address if the overflow flag is not set.",
+        exec: |cpu, a| Ok((!cpu.flags().v).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JE", opcode: 0x3b, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if equal, jumps to a memory address if the zero flag is set.",
+        exec: |cpu, a| Ok(cpu.flags().z.then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JNE", opcode: 0x3c, operands: &[Pointer], flow: Flow::Branch,
+        description: "Jump if not equal, jumps to a memory address if the zero flag is not set.",
+        exec: |cpu, a| Ok((!cpu.flags().z).then_some(a[0] as usize)),
+    },
+    Instruction {
+        mnemonic: "JMP", opcode: 0x3d, operands: &[Indexed], flow: Flow::Indirect,
+        description: "Jump indexed, jumps to the address in a register plus an offset, for jump tables.",
+        exec: |_, a| Ok(Some(a[0] as usize)),
+    },
+
+    Instruction {
+        mnemonic: "SCF", opcode: 0x40, operands: &[], flow: Flow::Next,
+        description: "Set carry flag, sets the carry flag.",
+        exec: |cpu, _| { cpu.set_carry(true); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "CCF", opcode: 0x41, operands: &[], flow: Flow::Next,
+        description: "Clear carry flag, clears the carry flag.",
+        exec: |cpu, _| { cpu.set_carry(false); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "EI", opcode: 0x42, operands: &[], flow: Flow::Next,
+        description: "Enable interrupts, lets pending IRQs jump to their handlers.",
+        exec: |cpu, _| { cpu.set_interrupts_enabled(true); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "DI", opcode: 0x43, operands: &[], flow: Flow::Next,
+        description: "Disable interrupts, IRQs stay pending until EI.",
+        exec: |cpu, _| { cpu.set_interrupts_enabled(false); Ok(None) },
+    },
+
+    Instruction {
+        mnemonic: "PUSH", opcode: 0x50, operands: &[Register], flow: Flow::Next,
+        description: "Push, moves the stack pointer down by 2 and stores a register at the new top of the stack.",
+        exec: |cpu, a| { cpu.push(cpu.reg(a[0] as u8))?; Ok(None) },
+    },
+    Instruction {
+        mnemonic: "POP", opcode: 0x51, operands: &[Register], flow: Flow::Next,
+        description: "Pop, loads a register from the top of the stack and moves the stack pointer up by 2.",
+        exec: |cpu, a| { let n = cpu.pop()?; cpu.set_reg(a[0] as u8, n); Ok(None) },
+    },
+    Instruction {
+        mnemonic: "CALL", opcode: 0x52, operands: &[Pointer], flow: Flow::Jump,
+        description: "Call, pushes the address of the next instruction and jumps to a subroutine.",
+        exec: |cpu, a| { cpu.push((cpu.pc() as u16).wrapping_add(3))?; Ok(Some(a[0] as usize)) },
+    },
+    Instruction {
+        mnemonic: "RET", opcode: 0x53, operands: &[], flow: Flow::Return,
+        description: "Return, pops an address off the stack and jumps back to it.",
+        exec: |cpu, _| Ok(Some(cpu.pop()? as usize)),
+    },
+    Instruction {
+        mnemonic: "CALL", opcode: 0x54, operands: &[Indexed], flow: Flow::Indirect,
+        description: "Call indexed, calls the subroutine at the address in a register plus an offset.",
+        exec: |cpu, a| { cpu.push((cpu.pc() as u16).wrapping_add(4))?; Ok(Some(a[0] as usize)) },
+    },
+    Instruction {
+        mnemonic: "IRET", opcode: 0x55, operands: &[], flow: Flow::Return,
+        description: "Return from interrupt, pops the flags and the address and enables interrupts again.",
+        exec: |cpu, _| {
+            let flags = cpu.pop()?;
+            let pc = cpu.pop()?;
+            cpu.set_flags(Flags::from_bits(flags));
+            cpu.set_interrupts_enabled(true);
+            Ok(Some(pc as usize))
+        },
+    },
+];
+
+pub fn by_opcode(opcode: u8) -> Option<&'static Instruction> {
+    INSTRUCTIONS.iter().find(|i| i.opcode == opcode)
+}
+
+/// Looks up an instruction by name, ignoring case. Names shared by several addressing modes give the first form.
+pub fn by_mnemonic(mnemonic: &str) -> Option<&'static Instruction> {
+    INSTRUCTIONS.iter().find(|i| i.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Turns the instruction at the start of `bytes` back into source text, returns it with its length.
+pub fn disassemble(bytes: &[u8]) -> Option<(String, usize)> {
+    let inst = by_opcode(*bytes.first()?)?;
+    if bytes.len() < inst.size() {return None}
+
+    let mut text = inst.mnemonic.to_owned();
+    let mut at = 1;
+    for (i, o) in inst.operands.iter().enumerate() {
+        text.push_str(if i == 0 {" "} else {", "});
+        match o {
+            Operand::Register => text.push_str(register_name(bytes[at])?),
+            Operand::Value | Operand::Pointer => text.push_str(&format!("0x{:04X}", (bytes[at] as u16) << 8 | bytes[at + 1] as u16)),
+            Operand::Indexed => {
+                let reg = register_name(bytes[at])?;
+                match (bytes[at + 1] as u16) << 8 | bytes[at + 2] as u16 {
+                    0 => text.push_str(&format!("[{reg}]")),
+                    n => text.push_str(&format!("[{reg}+0x{n:04X}]")),
+                }
+            }
+        }
+        at += o.size();
+    }
+    Some((text, inst.size()))
+}
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::{IRQ_KEYBOARD, PORTS};
+
+/// Word port. Bit 0 is set while a key is waiting in `KEYBOARD_DATA`, bit 1 can be set to keep raising
+/// `IRQ_KEYBOARD` for as long as one is.
+pub const KEYBOARD_STATUS: usize = PORTS + 0x20;
+/// Word port holding the oldest key not taken yet, storing anything to it takes the key and shows the next one.
+pub const KEYBOARD_DATA: usize = PORTS + 0x22;
+
+pub const STATUS_READY: u16 = 1 << 0;
+pub const STATUS_IRQ: u16 = 1 << 1;
+
+/// How many keys are kept before new ones are dropped.
+pub const BUFFER: usize = 16;
+
+/// The keyboard behind the keyboard ports, it buffers keys until the program takes them.
+#[derive(Debug, Clone, Default)]
+pub struct Keyboard {
+    keys: VecDeque<u8>,
+    irq: bool,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(&mut self, key: u8) {
+        if self.keys.len() < BUFFER { self.keys.push_back(key) }
+    }
+
+    pub fn status(&self) -> u16 {
+        (!self.keys.is_empty() as u16 * STATUS_READY) | (self.irq as u16 * STATUS_IRQ)
+    }
+
+    /// The waiting key, 0 if there isn't one.
+    pub fn data(&self) -> u16 {
+        self.keys.front().copied().unwrap_or(0) as u16
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.irq = false;
+    }
+}
+
+impl WordPorts for Keyboard {
+    fn name(&self) -> &str { "Keyboard" }
+    fn range(&self) -> RangeInclusive<usize> { KEYBOARD_STATUS..=KEYBOARD_DATA + 1 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            KEYBOARD_STATUS => self.status(),
+            KEYBOARD_DATA => self.data(),
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, addr: usize, n: u16) {
+        match addr {
+            KEYBOARD_STATUS => self.irq = n & STATUS_IRQ != 0,
+            KEYBOARD_DATA => { self.keys.pop_front(); }
+            _ => {}
+        }
+    }
+
+    /// Held up while a key is waiting, if the program asked for it.
+    fn irq(&self) -> Option<u8> {
+        (self.irq && !self.keys.is_empty()).then_some(IRQ_KEYBOARD)
+    }
+
+    fn reset(&mut self) { self.clear() }
+}
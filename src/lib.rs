@@ -0,0 +1,15 @@
+pub mod asm;
+pub mod bus;
+pub mod console;
+pub mod cpu;
+pub mod framebuffer;
+pub mod isa;
+pub mod keyboard;
+pub mod panel;
+pub mod random;
+pub mod sound;
+pub mod timer;
+
+pub use asm::assemble;
+pub use bus::{Bus, Device, Region, WordPorts};
+pub use cpu::{Cpu, Fault, Flags};
@@ -3,15 +3,77 @@
 use macroquad::prelude::*;
 use macroquad::audio::*;
 use rand::gen_range;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use std::fs;
 use std::env;
 
+use pc_visualizer::isa::{self, Flow, Operand};
+use pc_visualizer::console::{self, Console};
+use pc_visualizer::framebuffer::{self, Framebuffer};
+use pc_visualizer::keyboard::Keyboard;
+use pc_visualizer::panel::{self, Panel};
+use pc_visualizer::random::Random;
+use pc_visualizer::sound;
+use pc_visualizer::timer::Timer;
+use pc_visualizer::cpu::{REGISTERS, STACK_TOP};
+use pc_visualizer::{assemble, Cpu, Region};
+
 fn smooth_step(x: f32) -> f32 {
     let n = 3.;
     x.powf(n)/(x.powf(n)+(1.0-x).powf(n))
 }
 
+/// What the hex digits being typed will be written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Cell(usize),
+    Register(u8),
+    Pc,
+    Sp,
+}
+
+/// Where a register is drawn in the CPU box, two columns of four, unscaled.
+fn register_position(r: usize) -> (f32, f32) {
+    (206. + (r / 4) as f32 * 92., 300. + (r % 4) as f32 * 25.)
+}
+
+/// The colour of memory in a region, for the tape outlines and the RAM window.
+fn region_color(region: Region) -> Color {
+    match region {
+        Region::Ram => WHITE,
+        Region::Rom => GOLD,
+        Region::Data => PINK,
+        Region::NoAccess => DARKGRAY,
+    }
+}
+
+/// A loop of a square wave as a 16-bit mono WAV file, for playing the sound device's tones. It's a whole number of
+/// periods at least 10 ms long so it loops without clicks, and every pitch only costs a few hundred bytes.
+fn tone_wav(frequency: u16) -> Vec<u8> {
+    let rate = 22050u32;
+    let period = rate as f32 / frequency as f32;
+    let samples = (period * (rate as f32 / 100. / period).ceil()).round() as u32;
+
+    let mut wav = Vec::new();
+    wav.extend(b"RIFF");
+    wav.extend((36 + samples * 2).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes()); // PCM
+    wav.extend(1u16.to_le_bytes()); // Mono
+    wav.extend(rate.to_le_bytes());
+    wav.extend((rate * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend((samples * 2).to_le_bytes());
+    for i in 0..samples {
+        let high = (i as f32 % period) < period / 2.;
+        wav.extend((if high {8000i16} else {-8000}).to_le_bytes());
+    }
+    wav
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Byte {
     Unknown,
@@ -19,66 +81,48 @@ enum Byte {
     Register,
     Value,
     Pointer,
+    /// The offset of an indexed operand, added to the register before it.
+    Offset,
+    /// A cell last accessed by a single byte load or store.
+    Data,
 }
 
 #[macroquad::main("")]
 async fn main() {
     let args = env::args().collect::<Vec<_>>();
     
-    let mut ram = [(0u8, Byte::Unknown); 0x1_0000];
+    let mut cpu = Cpu::new();
+    let mut tags = [Byte::Unknown; 0x1_0000];
     
+    let mut errors: Vec<String> = Vec::new();
+
     if args.len() > 1 {
-        let file = fs::read_to_string(&args[1]).unwrap();
-        let bytes = assemble(file);
-        
-        for i in 0..bytes.len() {
-            ram[i].0 = bytes[i];
+        match fs::read_to_string(&args[1]) {
+            Ok(file) => match assemble(file) {
+                Ok(program) => program.load(&mut cpu),
+                Err(e) => errors = e.iter().map(|e| format!("{}:{}", args[1], e)).collect(),
+            },
+            Err(e) => errors.push(format!("{}: {}", args[1], e)),
         }
     }
 
+    if let Some(random) = cpu.bus_mut().device_mut::<Random>() {
+        *random = Random::new((macroquad::miniquad::date::now() * 1000.) as u64 as u16);
+    }
+
     let font = load_ttf_font_from_bytes(include_bytes!("Hack-Regular.ttf")).unwrap();
 
     let mut pointer: usize = 0;
-    let mut target_pointer = 0;
-
-    let mut reg_x: u16 = 0;
-    let mut reg_y: u16 = 0;
-
-    let mut cf = false;
-
-    let instructions = HashMap::from([
-        (0x00, ("NOP", "No operation, just increments the program counter, usually used for padding.")),
-        (0x01, ("HLT", "Halt, halts the program.")),
-
-        (0x10, ("MOV", "Move, copyies value from one register to another register.")),
-        (0x11, ("LOD", "Load, loads value from memory to a register.")),
-        (0x12, ("STO", "Store, stores value from a register to memory.")),
-        (0x13, ("LDR", "Load by register, loads value from memory to a register using X as an address.")),
-        (0x14, ("STR", "Store by register, stores value from a register to memory using X as an address.")),
-        (0x15, ("SWP", "Swap, swaps X and Y.")),
-        (0x16, ("LDI", "Load immediate, loads value into a register.")),
-
-        (0x20, ("ADD", "Add, adds the Y value to X.")),
-        (0x21, ("SUB", "Subtract, subtracts the Y value from X.")),
-        (0x22, ("MUL", "Multiply, multiplies X by Y.")),
-        (0x23, ("DIV", "Divide, divides X by Y.")),
-        (0x24, ("MOD", "Modulo, divides X by Y and returns the remainder.")),
-
-        (0x30, ("JMP", "Jump, jumps to a memory address.")),
-        (0x31, ("JZ", "Jump if zero, jumps to a memory address if X is zero.")),
-        (0x32, ("JNZ", "Jump if not zero, jumps to a memory address if X is not zero.")),
-        (0x33, ("JC", "Jump if carry, jumps to a memory address if carry flag is set.")),
-        (0x34, ("JNC", "Jump if not carry, jumps to a memory address if carry flag is not set.")),
-        (0x35, ("JGE", "Jump if greater or equal, jumps to a memory address if X is greater or equal to Y.")),
-        (0x36, ("JL", "Jump if less, jumps to a memory address if X is less than Y.")),
-
-        (0x40, ("SCF", "Set carry flag, sets the carry flag.")),
-        (0x41, ("CCF", "Clear carry flag, clears the carry flag.")),
-    ]);
-
-    let mut editing_index: Option<usize> = None;
+
+    let mut editing: Option<Edit> = None;
     let mut editing_value = String::new();
 
+    // Typing reaches the machine's keyboard only while the console has been clicked and no cell is being edited
+    let mut captured = false;
+
+    // The list of everything attached to the bus, opened with the Devices button
+    let mut devices_open = false;
+
     let mut offset = 0.;
     let mut anim = [0.; 3];
 
@@ -86,7 +130,6 @@ async fn main() {
     let mut turbo: bool = false;
     let mut next = false;
 
-    let mut halt = false;
     let mut played = false;
 
     let mut last_len = 0;
@@ -103,6 +146,14 @@ async fn main() {
     let type_sound = load_sound_from_bytes(include_bytes!("../sounds/type.wav")).await.unwrap();
     set_sound_volume(type_sound, 0.8);
 
+    let mut screen = Image::gen_image_color(framebuffer::WIDTH as u16, framebuffer::HEIGHT as u16, BLACK);
+    let screen_texture = Texture2D::from_image(&screen);
+    screen_texture.set_filter(FilterMode::Nearest);
+
+    // One looped sound per pitch, kept since macroquad can't unload sounds, and when each playing one should stop
+    let mut tones: HashMap<u16, Sound> = HashMap::new();
+    let mut stops: HashMap<u16, f64> = HashMap::new();
+
     let mut delta: f32;
 
     let mut scale: f32;
@@ -125,23 +176,21 @@ async fn main() {
         delta = 1./get_fps() as f32;
 
         { // Ticking
-            if pointer != target_pointer {
-                if turbo {pointer = target_pointer}
+            if pointer != cpu.pc() {
+                if turbo {pointer = cpu.pc()}
                 else {
                     offset += delta*1.;
                     if auto { offset += delta*1. }
-                    for i in 0..anim.len() {
-                        anim[i] = 0.;
-                    }
+                    anim.fill(0.);
                     last_len = 0;
                 }
             }
             if offset > 1. {
                 offset = 0.;
-                pointer = target_pointer;
+                pointer = cpu.pc();
             }
 
-            if !auto && pointer == target_pointer && anim[0] < 1. {
+            if !auto && pointer == cpu.pc() && anim[0] < 1. {
                 anim[0] += delta*2.;
             }
 
@@ -149,200 +198,273 @@ async fn main() {
             if anim[1] >= 1. && anim[2] < 1. { anim[2] += delta*1. }
         }
 
+        let overlay = !errors.is_empty() || devices_open;
+
         { // Interaction
-            if is_mouse_button_pressed(MouseButton::Left) {
+            if overlay {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    errors.clear();
+                    devices_open = false;
+                }
+            }
+            else if is_mouse_button_pressed(MouseButton::Left) {
                 editing_value.clear();
-                if mouse_position().0 > 210. *scale + frame.x && mouse_position().0 < (210. + 150.) *scale + frame.x && mouse_position().1 > 250. *scale + frame.y && mouse_position().1 < (250. + 70.) *scale + frame.y{
-                    editing_index = Some(0x100);
+                captured = false;
+                if mouse_position().0 > 210. *scale + frame.x && mouse_position().0 < (210. + 90.) *scale + frame.x && mouse_position().1 > 255. *scale + frame.y && mouse_position().1 < (255. + 23.) *scale + frame.y{
+                    editing = Some(Edit::Sp);
+                }
+                else if let Some(r) = (0..REGISTERS).find(|&r| {
+                    let (x, y) = register_position(r);
+                    mouse_position().0 > x *scale + frame.x && mouse_position().0 < (x + 88.) *scale + frame.x && mouse_position().1 > (y - 20.) *scale + frame.y && mouse_position().1 < (y + 4.) *scale + frame.y
+                }) {
+                    editing = Some(Edit::Register(r as u8));
                 }
-                else if mouse_position().0 > 210. *scale + frame.x && mouse_position().0 < (210. + 150.) *scale + frame.x && mouse_position().1 > 320. *scale + frame.y && mouse_position().1 < (320. + 60.) *scale + frame.y{
-                    editing_index = Some(0x101);
+                else if mouse_position().0 > 210. *scale + frame.x && mouse_position().0 < (210. + 170.) *scale + frame.x && mouse_position().1 > 395. *scale + frame.y && mouse_position().1 < (395. + 35.) *scale + frame.y{
+                    editing = Some(Edit::Pc);
                 }
-                else if mouse_position().0 > 210. *scale + frame.x && mouse_position().0 < (210. + 170.) *scale + frame.x && mouse_position().1 > 390. *scale + frame.y && mouse_position().1 < (390. + 40.) *scale + frame.y{
-                    editing_index = Some(0x102);
+                else if cpu.fault().is_some() && mouse_position().0 > 245. *scale + frame.x && mouse_position().0 < (245. + 240.) *scale + frame.x && mouse_position().1 > 205. *scale + frame.y && mouse_position().1 < (205. + 40.) *scale + frame.y{
+                    cpu.resume();
+                    play_sound_once(switch_sound);
                 }
-                else if mouse_position().0 > 380. *scale + frame.x && mouse_position().0 < (380. + 90.) *scale + frame.x && mouse_position().1 > 390. *scale + frame.y && mouse_position().1 < (390. + 40.) *scale + frame.y{
-                    cf =! cf;
+                else if mouse_position().0 > 396. *scale + frame.x && mouse_position().0 < (396. + 74.) *scale + frame.x && mouse_position().1 > 321. *scale + frame.y && mouse_position().1 < (321. + 100.) *scale + frame.y{
+                    let mut flags = cpu.flags();
+                    match ((mouse_position().1 - frame.y) / scale - 321.) as usize / 20 {
+                        0 => cpu.set_interrupts_enabled(!cpu.interrupts_enabled()),
+                        1 => flags.z = !flags.z,
+                        2 => flags.n = !flags.n,
+                        3 => flags.v = !flags.v,
+                        _ => flags.c = !flags.c,
+                    }
+                    cpu.set_flags(flags);
                     play_sound_once(switch_sound);
                 }
                 else if mouse_position().0 > 458. *scale + frame.x && mouse_position().0 < (458. + 20.) *scale + frame.x && mouse_position().1 > 260. *scale + frame.y && mouse_position().1 < (260. + 36.) *scale + frame.y{
-                    auto =! auto;
+                    auto = !auto;
                     play_sound_once(switch_sound);
                 }
                 else if mouse_position().0 > 428. *scale + frame.x && mouse_position().0 < (428. + 20.) *scale + frame.x && mouse_position().1 > 260. *scale + frame.y && mouse_position().1 < (260. + 36.) *scale + frame.y{
-                    turbo =! turbo;
+                    turbo = !turbo;
                     play_sound_once(switch_sound);
                 }
+                else if mouse_position().0 > 20. *scale + frame.x && mouse_position().0 < (20. + 144.) *scale + frame.x && mouse_position().1 > 76. *scale + frame.y && mouse_position().1 < (76. + 28.) *scale + frame.y{
+                    let i = ((mouse_position().0 - frame.x) / scale - 20.) as usize / 18;
+                    if let Some(panel) = cpu.bus_mut().device_mut::<Panel>() {
+                        panel.set_switches(panel.switches() ^ 0x80 >> i);
+                        play_sound_once(switch_sound);
+                    }
+                }
+                else if mouse_position().0 > 520. *scale + frame.x && mouse_position().0 < (520. + 270.) *scale + frame.x && mouse_position().1 > 10. *scale + frame.y && mouse_position().1 < (10. + 100.) *scale + frame.y{
+                    editing = None;
+                    captured = true;
+                }
+                else if mouse_position().0 > 432. *scale + frame.x && mouse_position().0 < (432. + 76.) *scale + frame.x && mouse_position().1 > 86. *scale + frame.y && mouse_position().1 < (86. + 22.) *scale + frame.y{
+                    editing = None;
+                    devices_open = true;
+                }
                 else {
-                    editing_index = None;
+                    editing = None;
                 }
             }
+            if is_key_pressed(KeyCode::Escape) { captured = false }
         }
         
         let thick = 6. *scale;
 
         { // Main draw
             for p in 0..0x1_0000 {
-                let x = ((p as f32 - pointer as f32 + 4.)  * 47. - (smooth_step(offset) * 47. * (target_pointer as f32 - pointer as f32))) * scale + frame.x;
+                let x = ((p as f32 - pointer as f32 + 4.)  * 47. - (smooth_step(offset) * 47. * (cpu.pc() as f32 - pointer as f32))) * scale + frame.x;
                 let y = 120. * scale + frame.y;
 
                 if x > -60. *scale + frame.x && x < screen_width() {
-                    if p <= 0xffff {
-                        let n = ram[p].0;
+                    let n = cpu.read(p);
 
-                        draw_rectangle_lines(
-                            x, 
-                            y, 
-                            50. *scale, 50. *scale, 
-                            thick, 
+                    draw_rectangle_lines(
+                        x, 
+                        y, 
+                        50. *scale, 50. *scale, 
+                        thick, 
+                        region_color(cpu.bus().region(p))
+                    );            
+
+                    let mut off = 0.;
+                    if tags[p] != Byte::Unknown {
+                        off = 8.;
+                        draw_line(
+                            x,
+                            y + 20. *scale,
+                            x + 50. *scale,
+                            y + 20. *scale,
+                            thick / 2.,
                             WHITE
-                        );            
-
-                        let mut off = 0.;
-                        if ram[p].1 != Byte::Unknown {
-                            off = 8.;
-                            draw_line(
-                                x,
-                                y + 20. *scale,
-                                x + 50. *scale,
-                                y + 20. *scale,
-                                thick / 2.,
-                                WHITE
-                            );
-                        }
-                        if ram[p].1 == Byte::Instruction { if let Some(inst) = instructions.get(&n) {
-                            draw_text_ex(
-                                &format!("{}", inst.0), 
-                                x+13. *scale, 
-                                y+16. *scale, 
-                                TextParams {
-                                    font,
-                                    font_size: (14. *scale) as u16,
-                                    color: RED,
-                                    ..Default::default()
-                                }
-                            );
-                        }}
+                        );
+                    }
+                    if tags[p] == Byte::Instruction { if let Some(inst) = isa::by_opcode(n) {
+                        draw_text_ex(
+                            inst.mnemonic, 
+                            x+13. *scale, 
+                            y+16. *scale, 
+                            TextParams {
+                                font,
+                                font_size: (14. *scale) as u16,
+                                color: RED,
+                                ..Default::default()
+                            }
+                        );
+                    }}
 
-                        if ram[p].1 == Byte::Register {
-                            let mut reg = "X";
-                            if ram[p].0 != 00 { reg = "Y" }
-                            draw_text_ex(
-                                reg, 
-                                x+20.5 *scale, 
-                                y+16. *scale, 
-                                TextParams {
-                                    font,
-                                    font_size: (14. *scale) as u16,
-                                    color: SKYBLUE,
-                                    ..Default::default()
-                                }
-                            );
-                        }
+                    if tags[p] == Byte::Register {
+                        let reg = isa::register_name(n).unwrap_or("?");
+                        draw_text_ex(
+                            reg, 
+                            x+(24.5 - 4. * reg.len() as f32) *scale, 
+                            y+16. *scale, 
+                            TextParams {
+                                font,
+                                font_size: (14. *scale) as u16,
+                                color: SKYBLUE,
+                                ..Default::default()
+                            }
+                        );
+                    }
 
-                        if p > 0 && ram[p].1 == Byte::Value && ram[p-1].1 == Byte::Value {
-                            draw_rectangle(
-                                x, 
-                                y + thick / 2., 
-                                thick / 2., 
-                                20. *scale - thick * 3. / 4., 
-                                Color::from_hex(0x181818)
-                            );
+                    if tags[p] == Byte::Data && (n.is_ascii_graphic() || n == b' ') {
+                        draw_text_ex(
+                            &format!("'{}'", n as char), 
+                            x+14. *scale, 
+                            y+16. *scale, 
+                            TextParams {
+                                font,
+                                font_size: (14. *scale) as u16,
+                                color: GREEN,
+                                ..Default::default()
+                            }
+                        );
+                    }
 
-                            let n = (ram[p-1].0 as u16) << 8 | ram[p].0 as u16;
-                            draw_text_ex(
-                                &format!("{:04x}", n).to_uppercase(), 
-                                x-15. *scale, 
-                                y+16. *scale, 
-                                TextParams {
-                                    font,
-                                    font_size: (14. *scale) as u16,
-                                    color: PURPLE,
-                                    ..Default::default()
-                                }
-                            );
-                        }
-                        if p > 0 && ram[p].1 == Byte::Pointer && ram[p-1].1 == Byte::Pointer {
-                            draw_rectangle(
-                                x, 
-                                y + thick / 2., 
-                                thick / 2., 
-                                20. *scale - thick * 3. / 4., 
-                                Color::from_hex(0x181818)
-                            );
+                    if p > 0 && tags[p] == Byte::Value && tags[p-1] == Byte::Value {
+                        draw_rectangle(
+                            x, 
+                            y + thick / 2., 
+                            thick / 2., 
+                            20. *scale - thick * 3. / 4., 
+                            Color::from_hex(0x181818)
+                        );
 
-                            let n = (ram[p-1].0 as u16) << 8 | ram[p].0 as u16;
-                            draw_text_ex(
-                                &format!("{:04x}", n).to_uppercase(), 
-                                x-15. *scale, 
-                                y+16. *scale, 
-                                TextParams {
-                                    font,
-                                    font_size: (14. *scale) as u16,
-                                    color: Color::from_hex(0xff8C00),
-                                    ..Default::default()
-                                }
-                            );
-                        }
+                        let n = cpu.read_word(p-1).unwrap_or(0);
+                        draw_text_ex(
+                            &format!("{:04x}", n).to_uppercase(), 
+                            x-15. *scale, 
+                            y+16. *scale, 
+                            TextParams {
+                                font,
+                                font_size: (14. *scale) as u16,
+                                color: PURPLE,
+                                ..Default::default()
+                            }
+                        );
+                    }
+                    if p > 0 && tags[p] == Byte::Offset && tags[p-1] == Byte::Offset {
+                        draw_rectangle(
+                            x,
+                            y + thick / 2.,
+                            thick / 2.,
+                            20. *scale - thick * 3. / 4.,
+                            Color::from_hex(0x181818)
+                        );
 
+                        let n = cpu.read_word(p-1).unwrap_or(0);
                         draw_text_ex(
-                            &format!("{:02x}", n).to_uppercase(), 
-                            x+14. *scale, 
-                            y+(32.+off) *scale, 
+                            &format!("+{:04x}", n).to_uppercase(),
+                            x-19. *scale,
+                            y+16. *scale,
                             TextParams {
                                 font,
-                                font_size: (18. *scale) as u16,
-                                color: WHITE,
+                                font_size: (14. *scale) as u16,
+                                color: SKYBLUE,
                                 ..Default::default()
                             }
                         );
+                    }
+                    if p > 0 && tags[p] == Byte::Pointer && tags[p-1] == Byte::Pointer {
+                        draw_rectangle(
+                            x, 
+                            y + thick / 2., 
+                            thick / 2., 
+                            20. *scale - thick * 3. / 4., 
+                            Color::from_hex(0x181818)
+                        );
 
+                        let n = cpu.read_word(p-1).unwrap_or(0);
                         draw_text_ex(
-                            &format!("{:04x}", p).to_uppercase(), 
-                            x+10. *scale, 
-                            y+60. *scale, 
+                            &format!("{:04x}", n).to_uppercase(), 
+                            x-15. *scale, 
+                            y+16. *scale, 
                             TextParams {
                                 font,
-                                font_size: (15. *scale) as u16,
-                                color: GRAY,
-                                rotation: 0.8,
+                                font_size: (14. *scale) as u16,
+                                color: Color::from_hex(0xff8c00),
                                 ..Default::default()
                             }
                         );
+                    }
 
-                        if is_mouse_button_pressed(MouseButton::Left) && mouse_position().0 > x && mouse_position().0 < x + 50.*scale && mouse_position().1 > y && mouse_position().1 < y + 50.*scale {
-                            editing_index = Some(p);
+                    draw_text_ex(
+                        &format!("{:02x}", n).to_uppercase(), 
+                        x+14. *scale, 
+                        y+(32.+off) *scale, 
+                        TextParams {
+                            font,
+                            font_size: (18. *scale) as u16,
+                            color: WHITE,
+                            ..Default::default()
                         }
+                    );
+
+                    draw_text_ex(
+                        &format!("{:04x}", p).to_uppercase(), 
+                        x+10. *scale, 
+                        y+60. *scale, 
+                        TextParams {
+                            font,
+                            font_size: (15. *scale) as u16,
+                            color: GRAY,
+                            rotation: 0.8,
+                            ..Default::default()
+                        }
+                    );
+
+                    if !overlay && is_mouse_button_pressed(MouseButton::Left) && mouse_position().0 > x && mouse_position().0 < x + 50.*scale && mouse_position().1 > y && mouse_position().1 < y + 50.*scale {
+                        editing = Some(Edit::Cell(p));
+                    }
+
+                    if let Some(Edit::Cell(index)) = editing {
+                        if index == p {
+                            draw_rectangle(
+                                x+14. *scale, 
+                                y+(18.+off) *scale, 
+                                21.*scale, 
+                                15.*scale, 
+                                Color::from_hex(0x101010)
+                            );
+
+                            draw_text_ex(
+                                &editing_value.to_uppercase(), 
+                                x+14. *scale, 
+                                y+(32.+off) *scale, 
+                                TextParams {
+                                    font,
+                                    font_size: (18. *scale) as u16,
+                                    color: WHITE,
+                                    ..Default::default()
+                                }
+                            );
 
-                        if let Some(index) = editing_index {
-                            if index == p {
-                                draw_rectangle(
-                                    x+14. *scale, 
-                                    y+(18.+off) *scale, 
-                                    21.*scale, 
-                                    15.*scale, 
-                                    Color::from_hex(0x101010)
-                                );
-
-                                draw_text_ex(
-                                    &editing_value.to_uppercase(), 
-                                    x+14. *scale, 
-                                    y+(32.+off) *scale, 
-                                    TextParams {
-                                        font,
-                                        font_size: (18. *scale) as u16,
-                                        color: WHITE,
-                                        ..Default::default()
-                                    }
-                                );
-
-                                if editing_value.len() >= 2 {
-                                    if let Ok(n) = u8::from_str_radix(&editing_value, 16) {
-                                        ram[p].0 = n;
-                                    }
-                                    editing_value.clear();
-                                    editing_index = None;
+                            if editing_value.len() >= 2 {
+                                if let Ok(n) = u8::from_str_radix(&editing_value, 16) {
+                                    cpu.write(p, n);
                                 }
+                                editing_value.clear();
+                                editing = None;
                             }
                         }
                     }
@@ -350,26 +472,31 @@ async fn main() {
             }
 
             if let Some(c) = get_char_pressed() {
-                editing_value.push(c);
+                if editing.is_some() { editing_value.push(c) }
+                else if captured && c.is_ascii() {
+                    if let Some(keyboard) = cpu.bus_mut().device_mut::<Keyboard>() {
+                        keyboard.press(if c == '\r' {b'\n'} else {c as u8});
+                    }
+                }
             }
 
         }
 
         { // Animations
             if !auto && anim[0] > 0. {
-                if let Some(inst) = instructions.get(&ram[pointer].0) {
+                if let Some(inst) = isa::by_opcode(cpu.read(pointer)) {
                     draw_rectangle_lines(
                         188. *scale + frame.x, 
                         (115. - 105. * smooth_step(anim[0])) *scale + frame.y, 
                         (50. + 188. * smooth_step(anim[1])) *scale, 
                         100. *scale * smooth_step(anim[0]), 
                         thick, 
-                        Color::from_hex(0xff8C00)
+                        Color::from_hex(0xff8c00)
                     );
 
-                    let chars = inst.1.to_owned().chars().collect::<Vec<_>>();
+                    let chars = inst.description.chars().collect::<Vec<_>>();
                     if (chars.len() as f32 * anim[2]) as usize <= chars.len() {
-                        let desc_full: String = chars[0..(chars.len() as f32 * anim[2]) as usize].into_iter().collect::<String>();
+                        let desc_full: String = chars[0..(chars.len() as f32 * anim[2]) as usize].iter().collect::<String>();
                         let desc: Vec<String> = desc_full.split(" ").map(|s| s.to_owned()).collect();
 
                         if desc_full.len() > last_len {
@@ -379,13 +506,13 @@ async fn main() {
 
                         let mut lines: Vec<String> = vec![String::new()];
 
-                        for w in 0..desc.len() {
+                        for word in &desc {
                             let last = lines.len() - 1;
-                            lines[last].push_str(&desc[w]);
+                            lines[last].push_str(word);
 
                             if lines[last].len() > 24 {
-                                lines[last] = lines[last].strip_suffix(&desc[w]).unwrap().to_owned();
-                                lines.push(desc[w].clone());
+                                lines[last] = lines[last].strip_suffix(word.as_str()).unwrap().to_owned();
+                                lines.push(word.clone());
                                 lines[last+1].push(' ');
                             }
                             else {
@@ -393,9 +520,9 @@ async fn main() {
                             }
                         }
 
-                        for l in 0..lines.len() {
+                        for (l, line) in lines.iter().enumerate() {
                             draw_text_ex(
-                                &format!("{}", lines[l].trim()), 
+                                line.trim(), 
                                 198. *scale + frame.x, 
                                 (30. + 18.*l as f32) *scale + frame.y, 
                                 TextParams {
@@ -428,7 +555,7 @@ async fn main() {
                 50. *scale, 
                 50. *scale, 
                 thick, 
-                Color::from_hex(0xff8C00)
+                Color::from_hex(0xff8c00)
             );
 
             draw_line(
@@ -437,7 +564,7 @@ async fn main() {
                 212. *scale + frame.x, 
                 230. *scale + frame.y,  
                 thick/2., 
-                Color::from_hex(0xff8C00)
+                Color::from_hex(0xff8c00)
             );
 
             draw_line(
@@ -446,7 +573,7 @@ async fn main() {
                 168. *scale + frame.x, 
                 408. *scale + frame.y,  
                 thick/2., 
-                Color::from_hex(0xff8C00)
+                Color::from_hex(0xff8c00)
             );
 
             draw_line(
@@ -455,7 +582,7 @@ async fn main() {
                 213.5 *scale + frame.x, 
                 230. *scale + frame.y, 
                 thick/2., 
-                Color::from_hex(0xff8C00)
+                Color::from_hex(0xff8c00)
             );
 
             draw_line( 
@@ -464,11 +591,11 @@ async fn main() {
                 205. *scale + frame.x, 
                 408. *scale + frame.y,    
                 thick/2., 
-                Color::from_hex(0xff8C00)
+                Color::from_hex(0xff8c00)
             );
 
             draw_text_ex(
-                &format!("Pointer: {}", format!("{:04x}",target_pointer).to_uppercase()), 
+                &format!("Pointer: {}", format!("{:04x}",cpu.pc()).to_uppercase()), 
                 210. *scale + frame.x, 
                 414. *scale + frame.y,  
                 TextParams {
@@ -479,439 +606,289 @@ async fn main() {
                 }
             );
 
-            draw_text_ex(
-                &format!("X: {:04x}", reg_x).to_uppercase(), 
-                210. *scale + frame.x, 
-                305. *scale + frame.y,  
-                TextParams {
-                    font,
-                    font_size: (30. *scale) as u16,
-                    color: WHITE,
-                    ..Default::default()
-                }
-            );
+            for r in 0..REGISTERS {
+                let (x, y) = register_position(r);
+                draw_text_ex(
+                    &format!("{:>2}: {:04x}", isa::REGISTER_NAMES[r], cpu.reg(r as u8)).to_uppercase(), 
+                    x *scale + frame.x, 
+                    y *scale + frame.y,  
+                    TextParams {
+                        font,
+                        font_size: (20. *scale) as u16,
+                        color: WHITE,
+                        ..Default::default()
+                    }
+                );
+            }
 
             draw_text_ex(
-                &format!("Y: {:04x}", reg_y).to_uppercase(), 
+                &format!("SP: {:04x}", cpu.sp()).to_uppercase(), 
                 210. *scale + frame.x, 
-                360. *scale + frame.y,  
+                272. *scale + frame.y,  
                 TextParams {
                     font,
-                    font_size: (30. *scale) as u16,
-                    color: WHITE,
+                    font_size: (14. *scale) as u16,
+                    color: SKYBLUE,
                     ..Default::default()
                 }
             );
+
+            if let Some(timer) = cpu.bus().device::<Timer>() {
+                draw_text_ex(
+                    &format!("Cycles: {:08x}", timer.cycles()).to_uppercase(), 
+                    296. *scale + frame.x, 
+                    272. *scale + frame.y,  
+                    TextParams {
+                        font,
+                        font_size: (12. *scale) as u16,
+                        color: GRAY,
+                        ..Default::default()
+                    }
+                );
+            }
         }
 
         { // CPU editing
-            if let Some(ei) = editing_index {
-                match ei {
-                    0x100 => {
+            if let Some(edit) = editing {
+                match edit {
+                    Edit::Register(r) => {
+                        let (x, y) = register_position(r as usize);
                         draw_rectangle(
-                            260. *scale + frame.x, 
-                            275. *scale + frame.y, 
-                            80. *scale, 
-                            40. *scale, 
+                            (x + 46.) *scale + frame.x, 
+                            (y - 19.) *scale + frame.y, 
+                            50. *scale, 
+                            25. *scale, 
                             Color::from_hex(0x101010)
                         );
                         draw_text_ex(
                             &editing_value.to_uppercase(), 
-                            265. *scale + frame.x, 
-                            305. *scale + frame.y, 
+                            (x + 48.) *scale + frame.x, 
+                            y *scale + frame.y, 
                             TextParams {
                                 font,
-                                font_size: (30. *scale) as u16,
+                                font_size: (20. *scale) as u16,
                                 color: WHITE,
                                 ..Default::default()
                             }
                         );
                         if editing_value.len() >= 4 {
-                            if let Ok(n) = u16::from_str_radix(&editing_value, 16) {reg_x = n}
+                            if let Ok(n) = u16::from_str_radix(&editing_value, 16) {cpu.set_reg(r, n)}
                             editing_value.clear();
-                            editing_index = None;
+                            editing = None;
                         }
-                    },
-                    0x101 => {
+                    }
+                    Edit::Pc => {
                         draw_rectangle(
-                            260. *scale + frame.x, 
-                            330. *scale + frame.y, 
-                            80. *scale, 
-                            40. *scale, 
+                            318. *scale + frame.x, 
+                            395. *scale + frame.y, 
+                            50. *scale, 
+                            25. *scale, 
                             Color::from_hex(0x101010)
                         );
                         draw_text_ex(
                             &editing_value.to_uppercase(), 
-                            265. *scale + frame.x, 
-                            360. *scale + frame.y, 
+                            318. *scale + frame.x, 
+                            415. *scale + frame.y, 
                             TextParams {
                                 font,
-                                font_size: (30. *scale) as u16,
+                                font_size: (20. *scale) as u16,
                                 color: WHITE,
                                 ..Default::default()
                             }
                         );
                         if editing_value.len() >= 4 {
-                            if let Ok(n) = u16::from_str_radix(&editing_value, 16) {reg_y = n}
+                            if let Ok(n) = usize::from_str_radix(&editing_value, 16) {cpu.set_pc(n)}
                             editing_value.clear();
-                            editing_index = None;
+                            editing = None;
                         }
                     }
-                    0x102 => {
+                    Edit::Sp => {
                         draw_rectangle(
-                            318. *scale + frame.x, 
-                            395. *scale + frame.y, 
-                            50. *scale, 
-                            25. *scale, 
+                            238. *scale + frame.x, 
+                            259. *scale + frame.y, 
+                            40. *scale, 
+                            17. *scale, 
                             Color::from_hex(0x101010)
                         );
                         draw_text_ex(
                             &editing_value.to_uppercase(), 
-                            318. *scale + frame.x, 
-                            415. *scale + frame.y, 
+                            242. *scale + frame.x, 
+                            272. *scale + frame.y, 
                             TextParams {
                                 font,
-                                font_size: (20. *scale) as u16,
-                                color: WHITE,
+                                font_size: (14. *scale) as u16,
+                                color: SKYBLUE,
                                 ..Default::default()
                             }
                         );
                         if editing_value.len() >= 4 {
-                            if let Ok(n) = usize::from_str_radix(&editing_value, 16) {target_pointer = n}
+                            if let Ok(n) = u16::from_str_radix(&editing_value, 16) {cpu.set_sp(n)}
                             editing_value.clear();
-                            editing_index = None;
+                            editing = None;
                         }
                     }
 
-                    _ => {}
+                    Edit::Cell(_) => {}
                 }
             }
         }
     
-        { // Carry flag
-            draw_text_ex(
-                "Carry flag:", 
-                380. *scale + frame.x, 
-                414. *scale + frame.y,  
-                TextParams {
-                    font,
-                    font_size: (10. *scale) as u16,
-                    color: WHITE,
-                    ..Default::default()
-                }
-            );
-            let mut color = Color::from_hex(0x550000);
-            if cf {color = Color::from_hex(0xFF0000)}
-            draw_circle(
-                460. *scale + frame.x, 
-                411. *scale + frame.y,
-                8. *scale, 
-                color
-            );
-        }
-
-        { // Labeling
-            ram[pointer].1 = Byte::Instruction;
-            let mut ip = target_pointer;
-            for _ in 0..100 {
-                ram[ip].1 = Byte::Instruction;
-                match ram[ip].0 {
-                    /* NOP */ 0x00 => {ip += 1},
-                    /* HLT */ 0x01 => {break},
-
-                    
-                    /* MOV */ 0x10 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ip += 2;
-                    },
-                    /* LOD */ 0x11 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        ram[ip + 3].1 = Byte::Pointer;
-                        ip += 4;
-                    }
-                    /* STO */ 0x12 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        ram[ip + 3].1 = Byte::Pointer;
-                        ip += 4;
-                    }
-                    /* LDR */ 0x13 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ip += 2;
-                    }
-                    /* STR */ 0x14 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ip += 2;
-                    }
-                    /* SWP */ 0x15 => {
-                        ip += 1;
+        { // Fault
+            if let Some(fault) = cpu.fault() {
+                draw_rectangle_lines(
+                    188. *scale + frame.x, 
+                    120. *scale + frame.y, 
+                    50. *scale, 
+                    50. *scale, 
+                    thick, 
+                    RED
+                );
+
+                draw_text_ex(
+                    &format!("FAULT at {:04X}: {}", cpu.pc(), fault), 
+                    250. *scale + frame.x, 
+                    222. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (13. *scale) as u16,
+                        color: RED,
+                        ..Default::default()
                     }
-                    /* LDI */ 0x16 => {
-                        ram[ip + 1].1 = Byte::Register;
-                        ram[ip + 2].1 = Byte::Value;
-                        ram[ip + 3].1 = Byte::Value;
-                        ip += 4;
+                );
+                draw_text_ex(
+                    "Click here to resume", 
+                    250. *scale + frame.x, 
+                    238. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (10. *scale) as u16,
+                        color: GRAY,
+                        ..Default::default()
                     }
-
-                    /* ADD */ 0x20 => {
-                        ip += 1;
-                    }
-                    /* SUB */ 0x21 => {
-                        ip += 1;
-                    }
-                    /* MUL */ 0x22 => {
-                        ip += 1;
-                    }
-                    /* DIV */ 0x23 => {
-                        ip += 1;
-                    }
-                    /* MOD */ 0x24 => {
-                        ip += 1;
+                );
+            }
+            else if let Some(irq) = cpu.interrupted() {
+                draw_text_ex(
+                    &format!("IRQ {irq}, jumping to handler"), 
+                    250. *scale + frame.x, 
+                    222. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (13. *scale) as u16,
+                        color: Color::from_hex(0xff8c00),
+                        ..Default::default()
                     }
+                );
+            }
+        }
 
-                    /* JMP */ 0x30 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
+        { // Flags
+            let flags = cpu.flags();
+            let lamps = [
+                ("Interrupts:", cpu.interrupts_enabled()),
+                ("Zero flag:", flags.z),
+                ("Negative flag:", flags.n),
+                ("Overflow flag:", flags.v),
+                ("Carry flag:", flags.c),
+            ];
+            for (i, (name, on)) in lamps.iter().enumerate() {
+                let y = 331. + 20. * i as f32;
+                let width = measure_text(name, Some(font), (10. *scale) as u16, 1.).width;
+                draw_text_ex(
+                    name, 
+                    446. *scale + frame.x - width, 
+                    (y + 3.) *scale + frame.y,  
+                    TextParams {
+                        font,
+                        font_size: (10. *scale) as u16,
+                        color: WHITE,
+                        ..Default::default()
                     }
-                    /* JZ */ 0x31 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
+                );
+                let mut color = Color::from_hex(0x550000);
+                if *on {color = Color::from_hex(0xff0000)}
+                draw_circle(
+                    460. *scale + frame.x, 
+                    y *scale + frame.y,
+                    8. *scale, 
+                    color
+                );
+            }
+        }
 
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
-                    /* JNZ */ 0x32 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
-                    /* JC */ 0x33 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
-                    /* JNC */ 0x34 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
-                    /* JGE */ 0x35 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
-                    /* JL */ 0x36 => {
-                        ram[ip + 1].1 = Byte::Pointer;
-                        ram[ip + 2].1 = Byte::Pointer;
-                        
-                        if 0.5 < gen_range(0., 1.) { ip += 3 }
-                        else { ip = (ram[ip+1].0 as usize) << 8 | ram[ip+2].0 as usize }
-                    }
+        { // Labeling
+            tags[pointer] = Byte::Instruction;
+            let mut ip = cpu.pc();
+            for _ in 0..100 {
+                let Some(inst) = isa::by_opcode(cpu.read(ip)) else {break};
+                if ip + inst.size() > 0x1_0000 {break}
+
+                tags[ip] = Byte::Instruction;
+                let mut at = ip + 1;
+                for o in inst.operands {
+                    let tag = match o {
+                        Operand::Register => Byte::Register,
+                        Operand::Value => Byte::Value,
+                        Operand::Pointer => Byte::Pointer,
+                        Operand::Indexed => Byte::Offset,
+                    };
+                    tags[at..at + o.size()].fill(tag);
+                    if *o == Operand::Indexed { tags[at] = Byte::Register }
+                    at += o.size();
+                }
 
-                    /* SCF */ 0x40 => {
-                        ip += 1;
-                    }
-                    /* CCF */ 0x41 => {
-                        ip += 1;
+                match inst.flow {
+                    Flow::Next => ip = at,
+                    Flow::Halt | Flow::Return | Flow::Indirect => break,
+                    Flow::Jump => ip = cpu.read_word(ip+1).unwrap_or(0) as usize,
+                    Flow::Branch => {
+                        if 0.5 < gen_range(0., 1.) { ip = at }
+                        else { ip = cpu.read_word(ip+1).unwrap_or(0) as usize }
                     }
-
-
-                    _ => {break}
                 }
             }
         }
 
         { // Execution
-            if pointer == target_pointer && (auto || next) {
+            if pointer == cpu.pc() && (auto || next) {
                 next = false;
                 played = false;
 
-                match ram[pointer].0 {
-                    /* NOP */ 0x00 => {target_pointer += 1},
-                    /* HLT */ 0x01 => {halt = true},
-
-                    /* MOV */ 0x10 => {
-                        if ram[pointer+1].0 == 0x00 {
-                            reg_x = reg_y;
-                        }
-                        else {
-                            reg_y = reg_x;
-                        }
-                        target_pointer += 2;
-                    },
-                    /* LOD */ 0x11 => {
-                        let addr = (ram[pointer+2].0 as usize) << 8 | ram[pointer+3].0 as usize;
-                        let n = (ram[addr].0 as u16) << 8 | ram[addr+1].0 as u16;
-
-                        if ram[pointer+1].0 == 0x00 {
-                            reg_x = n;
-                        }
-                        else {
-                            reg_y = n;
-                        }
-                        target_pointer += 4;
-                    }
-                    /* STO */ 0x12 => {
-                        let addr = (ram[pointer+2].0 as usize) << 8 | ram[pointer+3].0 as usize;
-
-                        if ram[pointer+1].0 == 0x00 {
-                            ram[addr].0 = (reg_x >> 8) as u8;
-                            ram[addr+1].0 = reg_x as u8;
-                        }
-                        else {
-                            ram[addr].0 = (reg_y >> 8) as u8;
-                            ram[addr+1].0 = reg_y as u8;
-                        }
-                        target_pointer += 4;
-                    }
-                    /* LDR */ 0x13 => {
-                        let n = (ram[reg_x as usize].0 as u16) << 8 | ram[(reg_x + 1) as usize].0 as u16;
-
-                        if ram[pointer+1].0 == 0x00 {
-                            reg_x = n;
-                        }
-                        else {
-                            reg_y = n;
-                        }
-                        target_pointer += 2;
-                    }
-                    /* STR */ 0x14 => {
-                        if ram[pointer+1].0 == 0x00 {
-                            ram[reg_x as usize].0 = (reg_x >> 8) as u8;
-                            ram[(reg_x+1) as usize].0 = reg_x as u8;
-                        }
-                        else {
-                            ram[reg_x as usize].0 = (reg_y >> 8) as u8;
-                            ram[(reg_x+1) as usize].0 = reg_y as u8;
-                        }
-                        target_pointer += 2;
-                    }
-                    /* SWP */ 0x15 => {
-                        let tmp = reg_x;
-                        reg_x = reg_y;
-                        reg_y = tmp;
-                        target_pointer += 1;
-                    }
-                    /* LDI */ 0x16 => {
-                        let n = (ram[pointer+2].0 as u16) << 8 | ram[pointer+3].0 as u16;
-                        
-                        if ram[pointer+1].0 == 0x00 {
-                            reg_x = n;
-                        }
-                        else {
-                            reg_y = n;
-                        }
-                        target_pointer += 4;
-                    }
-
-                    /* ADD */ 0x20 => {
-                        reg_x = reg_x.wrapping_add(reg_y);
-                        target_pointer += 1;
-
-                        if reg_x as u32 + reg_y as u32 > u16::MAX as u32 { cf = true }
-                    }
-                    /* SUB */ 0x21 => {
-                        reg_x = reg_x.wrapping_sub(reg_y);
-                        target_pointer += 1;
-
-                        if reg_x as u32 - reg_y as u32 > u16::MIN as u32 { cf = true }
-                    }
-                    /* MUL */ 0x22 => {
-                        reg_x *= reg_y;
-                        target_pointer += 1;
-                    }
-                    /* DIV */ 0x23 => {
-                        reg_x /= reg_y;
-                        target_pointer += 1;
-                    }
-                    /* MOD */ 0x24 => {
-                        reg_x %= reg_y;
-                        target_pointer += 1;
-                    }
-
-                    /* JMP */ 0x30 => {
-                        target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                    }
-                    /* JZ */ 0x31 => {
-                        if reg_x == 0 {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-                    /* JNZ */ 0x32 => {
-                        if reg_x != 0 {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-                    /* JC */ 0x33 => {
-                        if cf {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-                    /* JNC */ 0x34 => {
-                        if !cf {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-                    /* JGE */ 0x35 => {
-                        if reg_x >= reg_y {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-                    /* JL */ 0x36 => {
-                        if reg_x < reg_y {
-                            target_pointer = (ram[pointer+1].0 as usize) << 8 | ram[pointer+2].0 as usize;
-                        }
-                        else {
-                            target_pointer += 3;
-                        }
-                    }
-
-                    /* SCF */ 0x40 => {
-                        cf = true;
-                        target_pointer += 1;
-                    }
-                    /* CCF */ 0x41 => {
-                        cf = false;
-                        target_pointer += 1;
-                    }
+                if cpu.fault().is_none() && cpu.step().is_err() {
+                    play_sound_once(halt_sound);
+                }
+                if let Some(addr) = cpu.touched() {
+                    tags[addr] = Byte::Data;
+                }
+            }
 
-                    _ => {}
+            let played_tones = cpu.bus_mut().device_mut::<sound::Sound>().map(|s| s.take_tones()).unwrap_or_default();
+            for tone in played_tones {
+                if let Entry::Vacant(entry) = tones.entry(tone.frequency) {
+                    let Ok(sound) = load_sound_from_bytes(&tone_wav(tone.frequency)).await else {continue};
+                    entry.insert(sound);
                 }
+
+                // A new tone at the same pitch takes over from the one still playing
+                let sound = tones[&tone.frequency];
+                if stops.contains_key(&tone.frequency) { stop_sound(sound) }
+                play_sound(sound, PlaySoundParams { looped: true, volume: tone.volume as f32 / 255. * 0.5 });
+                stops.insert(tone.frequency, get_time() + tone.duration as f64 / 1000.);
             }
 
+            stops.retain(|frequency, &mut stop| {
+                if get_time() < stop {return true}
+                stop_sound(tones[frequency]);
+                false
+            });
+
             if offset > 0.7 && !played && !turbo {
-                if ram[target_pointer].0 == 1 { play_sound_once(halt_sound) }
+                if isa::by_opcode(cpu.read(cpu.pc())).map(|i| i.flow) == Some(Flow::Halt) { play_sound_once(halt_sound) }
                 else { play_sound_once(next_sound) }
                 played = true;
             }
 
-            if pointer == target_pointer && is_key_down(KeyCode::Space) {
+            if pointer == cpu.pc() && is_key_down(KeyCode::Space) && !captured {
                 next = true;
             }
         }
@@ -942,7 +919,7 @@ async fn main() {
             );
 
             let mut color = Color::from_hex(0x550000);
-            if auto {color = Color::from_hex(0xFF0000)}
+            if auto {color = Color::from_hex(0xff0000)}
 
             draw_rectangle(
                 458. *scale + frame.x + thick * 3. / 4., 
@@ -978,7 +955,7 @@ async fn main() {
             );
 
             let mut color = Color::from_hex(0x300030);
-            if turbo {color = Color::from_hex(0xB000F0)}
+            if turbo {color = Color::from_hex(0xb000f0)}
 
             draw_rectangle(
                 428. *scale + frame.x + thick * 3. / 4., 
@@ -999,182 +976,313 @@ async fn main() {
                 WHITE
             );
 
+            // The live part of the stack, from the stack pointer up to where it started
+            let stack = cpu.sp() as usize..STACK_TOP as usize;
+
             for y in 0..16 {
-                for x in 0..8 {
+                for x in 0..16 {
+                    let addr = 0xff00 + y*16 + x;
+                    let mut color = region_color(cpu.bus().region(addr));
+                    if stack.contains(&addr) {color = SKYBLUE}
+
                     draw_text_ex(
-                        &format!("{:02x}", ram[0xFF00 + y*16 + x].0).to_uppercase(), 
-                        (530. + x as f32*15.) *scale + frame.x, 
+                        &format!("{:02x}", cpu.read(addr)).to_uppercase(), 
+                        (530. + (x % 8) as f32*15. + (x / 8) as f32*133.) *scale + frame.x, 
                         (269. + y as f32*10.) *scale + frame.y, 
                         TextParams {
                             font,
                             font_size: (11. *scale) as u16,
-                            color: WHITE,
+                            color,
                             ..Default::default()
                         }
                     );
+                }
+            }
+        }
+
+        { // Console
+            if let Some(console) = cpu.bus().device::<Console>() {
+                let mut color = WHITE;
+                if captured {color = GREEN}
+                draw_rectangle_lines(
+                    520. *scale + frame.x, 
+                    10. *scale + frame.y, 
+                    270. *scale, 
+                    100. *scale, 
+                    thick, 
+                    color
+                );
+
+                let width = measure_text("M", Some(font), (10. *scale) as u16, 1.).width;
+                for (r, row) in console.rows().enumerate() {
                     draw_text_ex(
-                        &format!("{:02x}", ram[0xFF00 + y*16 + x+8].0).to_uppercase(), 
-                        (663. + x as f32*15.) *scale + frame.x, 
-                        (269. + y as f32*10.) *scale + frame.y, 
+                        &String::from_utf8_lossy(row), 
+                        530. *scale + frame.x, 
+                        (28. + r as f32*12.) *scale + frame.y, 
                         TextParams {
                             font,
-                            font_size: (11. *scale) as u16,
-                            color: WHITE,
+                            font_size: (10. *scale) as u16,
+                            color: GREEN,
                             ..Default::default()
                         }
                     );
                 }
+
+                let cursor = console.cursor();
+                if get_time() % 1. < 0.5 {
+                    draw_rectangle(
+                        530. *scale + frame.x + width * (cursor % console::COLUMNS) as f32, 
+                        (30. + (cursor / console::COLUMNS) as f32*12.) *scale + frame.y, 
+                        width, 
+                        1.5 *scale, 
+                        GREEN
+                    );
+                }
             }
+
+            // Only the headless runner mirrors the console to stdout
+            if let Some(console) = cpu.bus_mut().device_mut::<Console>() { console.take_output(); }
         }
-    }
-}
 
-fn assemble(mut asm: String) -> Vec<u8> {
-    let mut bytes = Vec::new();
+        { // Front panel
+            if let Some(panel) = cpu.bus().device::<Panel>() {
+                let digits = panel.digits();
+                for d in 0..4 {
+                    let segments = panel::SEGMENTS[(digits >> (12 - d * 4) & 0xf) as usize];
+                    let (x, y, w, h, t) = (24. + d as f32 * 26., 14., 20., 36., 3.);
+                    let rects = [
+                        (x + t, y, w - 2.*t, t),
+                        (x + w - t, y + t, t, h/2. - 1.5*t),
+                        (x + w - t, y + h/2. + t/2., t, h/2. - 1.5*t),
+                        (x + t, y + h - t, w - 2.*t, t),
+                        (x, y + h/2. + t/2., t, h/2. - 1.5*t),
+                        (x, y + t, t, h/2. - 1.5*t),
+                        (x + t, y + h/2. - t/2., w - 2.*t, t),
+                    ];
+                    for (i, (x, y, w, h)) in rects.iter().enumerate() {
+                        let mut color = Color::from_hex(0x550000);
+                        if segments >> i & 1 != 0 {color = Color::from_hex(0xff0000)}
+                        draw_rectangle(
+                            x *scale + frame.x, 
+                            y *scale + frame.y, 
+                            w *scale, 
+                            h *scale, 
+                            color
+                        );
+                    }
+                }
 
-    let mut labels: Vec<(String, usize)> = Vec::new();
+                let leds = panel.leds();
+                let switches = panel.switches();
+                for i in 0..8 {
+                    let x = 20. + i as f32 * 18.;
+
+                    let mut color = Color::from_hex(0x550000);
+                    if leds & 0x80 >> i != 0 {color = Color::from_hex(0xff0000)}
+                    draw_circle(
+                        (x + 7.) *scale + frame.x, 
+                        62. *scale + frame.y,
+                        6. *scale, 
+                        color
+                    );
 
-    let mut addr = 0;
-    for line in asm.lines() {
-        if line.is_empty() {continue}
-        let mut inst = line.split(' ').collect::<Vec<&str>>()[0];
+                    let on = switches & 0x80 >> i != 0;
+                    draw_rectangle(
+                        x *scale + frame.x, 
+                        76. *scale + frame.y, 
+                        14. * scale, 
+                        28. * scale,  
+                        Color::from_hex(0x080808)
+                    );
+                    draw_rectangle_lines(
+                        x *scale + frame.x, 
+                        76. *scale + frame.y, 
+                        14. * scale, 
+                        28. * scale, 
+                        thick / 2., 
+                        WHITE
+                    );
 
-        if inst.ends_with(':') {
-            inst = inst.trim_end_matches(':');
-            labels.push((inst.to_owned(), addr));
-        }
-        
-        match inst.to_uppercase().as_str() {
-            "NOP" => { addr += 1 }
-            "HLT" => { addr += 1 }
-
-            "MOV" => { addr += 2 }
-            "LOD" => { addr += 4 }
-            "STO" => { addr += 4 }
-            "LDR" => { addr += 2 }
-            "STR" => { addr += 2 }
-            "SWP" => { addr += 1 }
-            "LDI" => { addr += 4 }
-
-            "ADD" => { addr += 1 }
-            "SUB" => { addr += 1 }
-            "MUL" => { addr += 1 }
-            "DIV" => { addr += 1 }
-            "MOD" => { addr += 1 }
-
-            "JMP" => { addr += 3 }
-            "JZ" => { addr += 3 }
-            "JNZ" => { addr += 3 }
-            "JC" => { addr += 3 }
-            "JNC" => { addr += 3 }
-            "JGE" => { addr += 3 }
-            "JL" => { addr += 3 }
-
-            "SCF" => { addr += 1 }
-            "CCF" => { addr += 1 }
-
-            _ => {}
+                    let mut color = Color::from_hex(0x550000);
+                    if on {color = Color::from_hex(0xff0000)}
+                    draw_rectangle(
+                        x *scale + frame.x + thick / 2., 
+                        (76. + 14. * !on as u32 as f32) *scale + frame.y + thick / 2., 
+                        14. * scale - thick, 
+                        14. * scale - thick, 
+                        color,
+                    );
+                }
+            }
         }
-    }
-
-    for label in labels {
-        asm = asm.replace(&label.0, &format!("{:04x}", label.1));
-    }
-    asm = asm.replace("x", "00");
-    asm = asm.replace("y", "01");
 
+        { // Framebuffer
+            if let Some(fb) = cpu.bus().device::<Framebuffer>() {
+                draw_rectangle_lines(
+                    14. *scale + frame.x, 
+                    250. *scale + frame.y, 
+                    140. *scale, 
+                    140. *scale, 
+                    thick, 
+                    WHITE
+                );
+
+                for y in 0..framebuffer::HEIGHT {
+                    for x in 0..framebuffer::WIDTH {
+                        let color = framebuffer::PALETTE[fb.pixel(x, y) as usize];
+                        screen.set_pixel(x as u32, y as u32, Color::from_hex(color));
+                    }
+                }
+                screen_texture.update(&screen);
+
+                draw_texture_ex(
+                    screen_texture, 
+                    20. *scale + frame.x, 
+                    256. *scale + frame.y, 
+                    WHITE, 
+                    DrawTextureParams {
+                        dest_size: Some(vec2(128. *scale, 128. *scale)),
+                        ..Default::default()
+                    }
+                );
+            }
+        }
 
-    for line in asm.lines() {
-        if line.is_empty() {continue}
+        { // Devices
+            draw_rectangle_lines(
+                432. *scale + frame.x, 
+                86. *scale + frame.y, 
+                76. *scale, 
+                22. *scale, 
+                thick / 2., 
+                WHITE
+            );
+            draw_text_ex(
+                "Devices", 
+                442. *scale + frame.x, 
+                101. *scale + frame.y, 
+                TextParams {
+                    font,
+                    font_size: (14. *scale) as u16,
+                    color: WHITE,
+                    ..Default::default()
+                }
+            );
 
-        let line = line.trim();
-        let mut line = line.split(' ');
-        let inst = line.next().unwrap();
-        let args: Vec<u16> = line.map(|s| u16::from_str_radix(s, 16).unwrap()).collect();
+            if devices_open {
+                draw_rectangle(
+                    frame.x, 
+                    frame.y, 
+                    800. *scale, 
+                    450. *scale, 
+                    Color::new(0.05, 0.05, 0.05, 0.92)
+                );
+
+                draw_text_ex(
+                    "Devices on the bus:", 
+                    30. *scale + frame.x, 
+                    50. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (24. *scale) as u16,
+                        color: WHITE,
+                        ..Default::default()
+                    }
+                );
 
-        match inst.to_uppercase().as_str() {
-            "NOP" => { bytes.push(0x00) }
-            "HLT" => { bytes.push(0x01) }
+                // Later devices shadow earlier ones, the first bytes are read through the device itself
+                for (i, device) in cpu.bus().devices().take(18).enumerate() {
+                    let range = device.range();
+                    let region = cpu.bus().region(*range.start());
+                    let bytes = range.clone().take(8).map(|a| format!("{:02x}", device.read(a))).collect::<Vec<_>>();
+                    draw_text_ex(
+                        &format!("{:<12} {:04x}-{:04x}  {:<8}  {}", device.name(), range.start(), range.end(), region.name(), bytes.join(" ")).to_uppercase(), 
+                        30. *scale + frame.x, 
+                        (85. + i as f32*18.) *scale + frame.y, 
+                        TextParams {
+                            font,
+                            font_size: (14. *scale) as u16,
+                            color: region_color(region),
+                            ..Default::default()
+                        }
+                    );
+                }
 
-            "MOV" => { 
-                bytes.push(0x10);      
-                bytes.push(args[0] as u8);          
-            }
-            "LOD" => { 
-                bytes.push(0x11);
-                bytes.push(args[0] as u8);
-                bytes.push((args[1] >> 8) as u8);
-                bytes.push(args[1] as u8);
-            }
-            "STO" => { 
-                bytes.push(0x12);
-                bytes.push(args[0] as u8);
-                bytes.push((args[1] >> 8) as u8);
-                bytes.push(args[1] as u8);
-            }
-            "LDR" => { 
-                bytes.push(0x13);
-                bytes.push(args[0] as u8);
-            }
-            "STR" => { 
-                bytes.push(0x14);
-                bytes.push(args[0] as u8);
-            }
-            "SWP" => { bytes.push(0x15) }
-            "LDI" => { 
-                bytes.push(0x16);
-                bytes.push(args[0] as u8);
-                bytes.push((args[1] >> 8) as u8);
-                bytes.push(args[1] as u8);
+                draw_text_ex(
+                    "Click anywhere to dismiss", 
+                    30. *scale + frame.x, 
+                    430. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (12. *scale) as u16,
+                        color: GRAY,
+                        ..Default::default()
+                    }
+                );
             }
+        }
 
-            "ADD" => { bytes.push(0x20) }
-            "SUB" => { bytes.push(0x21) }
-            "MUL" => { bytes.push(0x22) }
-            "DIV" => { bytes.push(0x23) }
-            "MOD" => { bytes.push(0x24) }
-
-            "JMP" => {     
-                bytes.push(0x30);
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JZ"  => {     
-                bytes.push(0x31);
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JNZ" => {     
-                bytes.push(0x32);
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JC"  => {     
-                bytes.push(0x33);
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JNC" => {     
-                bytes.push(0x34); 
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JGE" => {     
-                bytes.push(0x35); 
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
-            "JL"  => {     
-                bytes.push(0x36); 
-                bytes.push((args[0] >> 8) as u8);
-                bytes.push(args[0] as u8);
-            }
+        { // Error overlay
+            if !errors.is_empty() {
+                draw_rectangle(
+                    frame.x, 
+                    frame.y, 
+                    800. *scale, 
+                    450. *scale, 
+                    Color::new(0.05, 0.05, 0.05, 0.92)
+                );
+
+                draw_text_ex(
+                    "Assembly failed:", 
+                    30. *scale + frame.x, 
+                    50. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (24. *scale) as u16,
+                        color: RED,
+                        ..Default::default()
+                    }
+                );
 
-            "SCF" => { bytes.push(0x37) }
-            "CCF" => { bytes.push(0x38) }
+                for (i, e) in errors.iter().take(18).enumerate() {
+                    draw_text_ex(
+                        e, 
+                        30. *scale + frame.x, 
+                        (85. + i as f32*18.) *scale + frame.y, 
+                        TextParams {
+                            font,
+                            font_size: (14. *scale) as u16,
+                            color: WHITE,
+                            ..Default::default()
+                        }
+                    );
+                }
+                if errors.len() > 18 {
+                    draw_text_ex(
+                        &format!("... and {} more", errors.len() - 18), 
+                        30. *scale + frame.x, 
+                        (85. + 18. * 18.) *scale + frame.y, 
+                        TextParams {
+                            font,
+                            font_size: (14. *scale) as u16,
+                            color: GRAY,
+                            ..Default::default()
+                        }
+                    );
+                }
 
-            _ => {}
+                draw_text_ex(
+                    "Click anywhere to dismiss", 
+                    30. *scale + frame.x, 
+                    430. *scale + frame.y, 
+                    TextParams {
+                        font,
+                        font_size: (12. *scale) as u16,
+                        color: GRAY,
+                        ..Default::default()
+                    }
+                );
+            }
         }
     }
-    bytes
 }
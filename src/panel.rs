@@ -0,0 +1,67 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::PORTS;
+
+/// Word port, its low byte lights the 8 LEDs with bit 7 on the left.
+pub const LEDS: usize = PORTS + 0x30;
+/// Word port shown as 4 hex digits on the seven-segment display.
+pub const DIGITS: usize = PORTS + 0x32;
+/// Word port, its low byte reads the 8 toggle switches with bit 7 on the left. Stores to it are ignored.
+pub const SWITCHES: usize = PORTS + 0x34;
+
+/// The segments lit for each hex digit. Bit 0 is the top segment, going clockwise to bit 5 and bit 6 is the middle.
+pub const SEGMENTS: [u8; 16] = [
+    0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07,
+    0x7f, 0x6f, 0x77, 0x7c, 0x39, 0x5e, 0x79, 0x71,
+];
+
+/// The front panel behind the panel ports. The switches belong to the user, the program can only read them.
+#[derive(Debug, Clone, Default)]
+pub struct Panel {
+    leds: u8,
+    digits: u16,
+    switches: u8,
+}
+
+impl Panel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn leds(&self) -> u8 { self.leds }
+    pub fn digits(&self) -> u16 { self.digits }
+    pub fn switches(&self) -> u8 { self.switches }
+
+    pub fn set_switches(&mut self, switches: u8) {
+        self.switches = switches;
+    }
+}
+
+impl WordPorts for Panel {
+    fn name(&self) -> &str { "Panel" }
+    fn range(&self) -> RangeInclusive<usize> { LEDS..=SWITCHES + 1 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            LEDS => self.leds as u16,
+            DIGITS => self.digits,
+            SWITCHES => self.switches as u16,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, addr: usize, n: u16) {
+        match addr {
+            LEDS => self.leds = n as u8,
+            DIGITS => self.digits = n,
+            _ => {}
+        }
+    }
+
+    /// Blanks the lights, the switches stay where the user left them.
+    fn reset(&mut self) {
+        self.leds = 0;
+        self.digits = 0;
+    }
+}
@@ -0,0 +1,61 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::PORTS;
+
+/// Word port with a new pseudo-random value every step, read-only.
+pub const RANDOM: usize = PORTS + 0x40;
+/// Word port holding the seed, storing it starts the sequence over from the new seed.
+pub const RANDOM_SEED: usize = PORTS + 0x42;
+
+/// A xorshift generator behind the random ports, the same seed always gives the same values.
+#[derive(Debug, Clone)]
+pub struct Random {
+    seed: u16,
+    state: u32,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u16) -> Self {
+        // Spread the seed over both halves, xorshift never leaves a zero state
+        Self { seed, state: (seed as u32) << 16 ^ seed as u32 ^ 0x2545_f491 }
+    }
+
+    pub fn seed(&self) -> u16 { self.seed }
+
+    pub fn value(&self) -> u16 {
+        (self.state >> 16) as u16
+    }
+}
+
+impl WordPorts for Random {
+    fn name(&self) -> &str { "Random" }
+    fn range(&self) -> RangeInclusive<usize> { RANDOM..=RANDOM_SEED + 1 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            RANDOM => self.value(),
+            RANDOM_SEED => self.seed,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, addr: usize, n: u16) {
+        if addr == RANDOM_SEED { *self = Self::new(n) }
+    }
+
+    fn tick(&mut self) {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+    }
+
+    /// Starts over from the same seed.
+    fn reset(&mut self) { *self = Self::new(self.seed) }
+}
@@ -0,0 +1,78 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::PORTS;
+
+/// Word port, the pitch of the next tone in Hz.
+pub const SOUND_FREQUENCY: usize = PORTS + 0x50;
+/// Word port, storing a length in milliseconds plays a tone with the current frequency and volume.
+pub const SOUND_DURATION: usize = PORTS + 0x52;
+/// Word port, the loudness of the next tone from 0 to 255.
+pub const SOUND_VOLUME: usize = PORTS + 0x54;
+
+/// A tone a program asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tone {
+    pub frequency: u16,
+    pub duration: u16,
+    pub volume: u8,
+}
+
+/// The tone generator behind the sound ports. It only queues tones, whoever runs the CPU plays or logs them.
+#[derive(Debug, Clone)]
+pub struct Sound {
+    frequency: u16,
+    volume: u8,
+    tones: Vec<Tone>,
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sound {
+    pub fn new() -> Self {
+        Self {
+            frequency: 0,
+            volume: 0xff,
+            tones: Vec::new(),
+        }
+    }
+
+    pub fn frequency(&self) -> u16 { self.frequency }
+    pub fn volume(&self) -> u8 { self.volume }
+
+    /// The tones played since the last call, oldest first.
+    pub fn take_tones(&mut self) -> Vec<Tone> {
+        std::mem::take(&mut self.tones)
+    }
+}
+
+impl WordPorts for Sound {
+    fn name(&self) -> &str { "Sound" }
+    fn range(&self) -> RangeInclusive<usize> { SOUND_FREQUENCY..=SOUND_VOLUME + 1 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            SOUND_FREQUENCY => self.frequency,
+            SOUND_VOLUME => self.volume as u16,
+            _ => 0,
+        }
+    }
+
+    /// A tone with no frequency or no length is silence and isn't queued.
+    fn store(&mut self, addr: usize, n: u16) {
+        match addr {
+            SOUND_FREQUENCY => self.frequency = n,
+            SOUND_VOLUME => self.volume = n.min(0xff) as u8,
+            SOUND_DURATION if self.frequency != 0 && n != 0 => {
+                self.tones.push(Tone { frequency: self.frequency, duration: n, volume: self.volume });
+            }
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) { *self = Self::new() }
+}
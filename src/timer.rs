@@ -0,0 +1,87 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::WordPorts;
+use crate::cpu::{IRQ_TIMER, PORTS};
+
+/// Word port, the value the countdown starts from. Storing it also restarts the countdown, 0 counts 65536 steps.
+pub const TIMER_RELOAD: usize = PORTS + 0x10;
+/// Word port holding the countdown, read-only.
+pub const TIMER_COUNT: usize = PORTS + 0x12;
+/// Word port with the `CONTROL_` bits. Storing it sets the enable bits and clears the overflow flag.
+pub const TIMER_CONTROL: usize = PORTS + 0x14;
+/// Two word ports making up a big-endian 32-bit count of every step since reset, storing to either clears it.
+pub const CYCLES: usize = PORTS + 0x16;
+
+/// The countdown runs while this is set.
+pub const CONTROL_ENABLE: u16 = 1 << 0;
+/// Keep raising `IRQ_TIMER` while the overflow flag is set.
+pub const CONTROL_IRQ: u16 = 1 << 1;
+/// Set when the countdown reaches 0 and reloads.
+pub const CONTROL_OVERFLOW: u16 = 1 << 2;
+
+/// The interval timer and cycle counter behind the timer ports, advanced once per step.
+#[derive(Debug, Clone, Default)]
+pub struct Timer {
+    reload: u16,
+    count: u16,
+    control: u16,
+    cycles: u32,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reload(&self) -> u16 { self.reload }
+    pub fn count(&self) -> u16 { self.count }
+    pub fn control(&self) -> u16 { self.control }
+    pub fn cycles(&self) -> u32 { self.cycles }
+
+}
+
+impl WordPorts for Timer {
+    fn name(&self) -> &str { "Timer" }
+    fn range(&self) -> RangeInclusive<usize> { TIMER_RELOAD..=CYCLES + 3 }
+
+    fn port(&self, addr: usize) -> u16 {
+        match addr {
+            TIMER_RELOAD => self.reload,
+            TIMER_COUNT => self.count,
+            TIMER_CONTROL => self.control,
+            CYCLES => (self.cycles >> 16) as u16,
+            a if a == CYCLES + 2 => self.cycles as u16,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, addr: usize, n: u16) {
+        match addr {
+            TIMER_RELOAD => {
+                self.reload = n;
+                self.count = n;
+            }
+            TIMER_CONTROL => self.control = n & (CONTROL_ENABLE | CONTROL_IRQ),
+            a if a == CYCLES || a == CYCLES + 2 => self.cycles = 0,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles = self.cycles.wrapping_add(1);
+        if self.control & CONTROL_ENABLE == 0 {return}
+
+        self.count = self.count.wrapping_sub(1);
+        if self.count == 0 {
+            self.count = self.reload;
+            self.control |= CONTROL_OVERFLOW;
+        }
+    }
+
+    /// Held up while the overflow flag is set, if the program asked for it.
+    fn irq(&self) -> Option<u8> {
+        (self.control & (CONTROL_IRQ | CONTROL_OVERFLOW) == CONTROL_IRQ | CONTROL_OVERFLOW).then_some(IRQ_TIMER)
+    }
+
+    fn reset(&mut self) { *self = Self::new() }
+}
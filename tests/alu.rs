@@ -0,0 +1,136 @@
+use pc_visualizer::{assemble, Cpu, Flags};
+
+/// Runs one instruction with X and Y set.
+fn exec(source: &str, x: u16, y: u16) -> Cpu {
+    exec_with(source, x, y, Flags::default())
+}
+
+/// Runs one instruction with X, Y and the flags set.
+fn exec_with(source: &str, x: u16, y: u16, before: Flags) -> Cpu {
+    let mut cpu = Cpu::new();
+    assemble(source.to_owned()).unwrap().load(&mut cpu);
+    cpu.set_x(x);
+    cpu.set_y(y);
+    cpu.set_flags(before);
+    cpu.step().unwrap();
+    cpu
+}
+
+fn flags(c: bool, z: bool, n: bool, v: bool) -> Flags {
+    Flags { c, z, n, v }
+}
+
+#[test]
+fn add_sets_carry_zero_negative_and_overflow() {
+    for (x, y, sum, expected) in [
+        (1, 1, 2, flags(false, false, false, false)),
+        (0xffff, 1, 0, flags(true, true, false, false)),
+        (0x7fff, 1, 0x8000, flags(false, false, true, true)),
+        (0x8000, 0x8000, 0, flags(true, true, false, true)),
+        (0xffff, 0xffff, 0xfffe, flags(true, false, true, false)),
+    ] {
+        let cpu = exec("add", x, y);
+        assert_eq!((cpu.x(), cpu.flags()), (sum, expected), "{x:04X} + {y:04X}");
+    }
+}
+
+#[test]
+fn sub_and_cmp_set_borrow_zero_negative_and_overflow() {
+    for (x, y, difference, expected) in [
+        (5, 3, 2, flags(false, false, false, false)),
+        (3, 5, 0xfffe, flags(true, false, true, false)),
+        (5, 5, 0, flags(false, true, false, false)),
+        (0x8000, 1, 0x7fff, flags(false, false, false, true)),
+        (0x7fff, 0xffff, 0x8000, flags(true, false, true, true)),
+    ] {
+        let cpu = exec("sub", x, y);
+        assert_eq!((cpu.x(), cpu.flags()), (difference, expected), "{x:04X} - {y:04X}");
+
+        let cpu = exec("cmp", x, y);
+        assert_eq!((cpu.x(), cpu.flags()), (x, expected), "CMP {x:04X}, {y:04X}");
+    }
+}
+
+#[test]
+fn logic_sets_zero_and_negative_and_clears_carry_and_overflow() {
+    for (mnemonic, x, y, result, expected) in [
+        ("and", 0xf0f0, 0xff00, 0xf000, flags(false, false, true, false)),
+        ("and", 0x00ff, 0xff00, 0, flags(false, true, false, false)),
+        ("or", 0x0f00, 0x00f0, 0x0ff0, flags(false, false, false, false)),
+        ("or", 0, 0, 0, flags(false, true, false, false)),
+        ("xor", 0xffff, 0xffff, 0, flags(false, true, false, false)),
+        ("xor", 0x00ff, 0xffff, 0xff00, flags(false, false, true, false)),
+        ("not", 0, 0x1234, 0xffff, flags(false, false, true, false)),
+        ("not", 0xffff, 0, 0, flags(false, true, false, false)),
+    ] {
+        let cpu = exec_with(mnemonic, x, y, flags(true, false, false, true));
+        assert_eq!((cpu.x(), cpu.y(), cpu.flags()), (result, y, expected), "{mnemonic} {x:04X}, {y:04X}");
+    }
+}
+
+#[test]
+fn shifts_and_rotates_move_bits_through_the_carry() {
+    for (mnemonic, x, carry, result, expected) in [
+        ("shl", 0x4000, false, 0x8000, flags(false, false, true, true)),
+        ("shl", 0xc000, false, 0x8000, flags(true, false, true, false)),
+        ("shl", 0x8000, false, 0, flags(true, true, false, true)),
+        ("shl", 0x0001, true, 0x0002, flags(false, false, false, false)),
+        ("shr", 0x0003, false, 0x0001, flags(true, false, false, false)),
+        ("shr", 0x8000, true, 0x4000, flags(false, false, false, false)),
+        ("shr", 0x0001, false, 0, flags(true, true, false, false)),
+        ("rol", 0x8000, false, 0, flags(true, true, false, false)),
+        ("rol", 0x0001, true, 0x0003, flags(false, false, false, false)),
+        ("rol", 0xc000, true, 0x8001, flags(true, false, true, false)),
+        ("ror", 0x0001, false, 0, flags(true, true, false, false)),
+        ("ror", 0x0002, true, 0x8001, flags(false, false, true, false)),
+        ("ror", 0x0003, true, 0x8001, flags(true, false, true, false)),
+    ] {
+        let cpu = exec_with(mnemonic, x, 0, flags(carry, false, false, false));
+        assert_eq!((cpu.x(), cpu.flags()), (result, expected), "{mnemonic} {x:04X} with C={carry}");
+    }
+}
+
+#[test]
+fn inc_and_dec_leave_the_carry_alone() {
+    for (source, x, carry, result, expected) in [
+        ("inc x", 0x0001, false, 0x0002, flags(false, false, false, false)),
+        ("inc x", 0x7fff, true, 0x8000, flags(true, false, true, true)),
+        ("inc x", 0xffff, false, 0, flags(false, true, false, false)),
+        ("inc x", 0xffff, true, 0, flags(true, true, false, false)),
+        ("dec x", 0x0001, false, 0, flags(false, true, false, false)),
+        ("dec x", 0x8000, false, 0x7fff, flags(false, false, false, true)),
+        ("dec x", 0, false, 0xffff, flags(false, false, true, false)),
+        ("dec x", 0, true, 0xffff, flags(true, false, true, false)),
+    ] {
+        let cpu = exec_with(source, x, 0, flags(carry, false, false, false));
+        assert_eq!((cpu.x(), cpu.flags()), (result, expected), "{source} {x:04X} with C={carry}");
+    }
+
+    let cpu = exec_with("inc y", 0, 0x0041, Flags::default());
+    assert_eq!((cpu.x(), cpu.y()), (0, 0x0042));
+}
+
+#[test]
+fn conditional_jumps_follow_the_flags() {
+    for (mnemonic, taken_when) in [
+        ("jn", flags(false, false, true, false)),
+        ("jv", flags(false, false, false, true)),
+        ("je", flags(false, true, false, false)),
+    ] {
+        for (set, taken) in [(taken_when, true), (Flags::default(), false)] {
+            let mut cpu = Cpu::new();
+            assemble(format!("{mnemonic} 0x0100")).unwrap().load(&mut cpu);
+            cpu.set_flags(set);
+            cpu.step().unwrap();
+            assert_eq!(cpu.pc(), if taken {0x100} else {3}, "{mnemonic} with {set:?}");
+        }
+    }
+
+    for (z, taken) in [(false, true), (true, false)] {
+        let mut cpu = Cpu::new();
+        assemble("jne 0x0100".to_owned()).unwrap().load(&mut cpu);
+        cpu.set_flags(flags(false, z, false, false));
+        cpu.step().unwrap();
+        assert_eq!(cpu.pc(), if taken {0x100} else {3}, "JNE with Z={z}");
+    }
+}
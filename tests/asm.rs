@@ -0,0 +1,107 @@
+use pc_visualizer::asm::AsmError;
+use pc_visualizer::assemble;
+
+/// The errors for `source`, which must fail to assemble.
+fn errors(source: &str) -> Vec<AsmError> {
+    assemble(source.to_owned()).expect_err(source)
+}
+
+#[test]
+fn overflowing_expressions_wrap_instead_of_panicking() {
+    const MIN: &str = "0x8000*0x8000*0x8000*0x8000*8";
+
+    for source in [format!("ldi x, {MIN}/-1"), format!("ldi x, -{MIN}")] {
+        assert_eq!(errors(&source)[0].message, "value out of range, expected a 16 bit value", "{source}");
+    }
+
+    let program = assemble(format!("ldi x, {MIN}%-1")).unwrap();
+    assert_eq!(program.segments[0].bytes, [0x16, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn mistakes_are_reported_with_their_position() {
+    let source = "
+start:
+    foo x
+    ldi x
+    mov q
+    ldi x, 0x8000*2
+    .db 256
+    jmp nowhere
+start:
+";
+    let found = errors(source).into_iter().map(|e| (e.line, e.column, e.token, e.message)).collect::<Vec<_>>();
+    let expected = [
+        (3, 5, "foo", "unknown mnemonic"),
+        (4, 5, "ldi", "expected 2 operand(s), found 1"),
+        (5, 9, "q", "bad register, expected X, Y or R0 to R7"),
+        (6, 12, "0x8000*2", "value out of range, expected a 16 bit value"),
+        (7, 9, "256", "value out of range, expected an 8 bit value"),
+        (8, 9, "nowhere", "undefined label"),
+        (9, 1, "start:", "duplicate label"),
+    ].map(|(line, column, token, message)| (line, column, token.to_owned(), message.to_owned()));
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn labels_are_never_mangled() {
+    // Keywords, a label that starts another one and a label spelled like a nearby hex literal
+    let program = assemble("
+            jmp next
+        loop:
+            jmp loop2
+        loop2:
+            jmp yield
+        next:
+            ldi x, 0xbeef
+        yield:
+            ldi y, beef
+        beef:
+            hlt
+    ".to_owned()).unwrap();
+
+    assert_eq!(program.segments[0].bytes, [
+        0x30, 0x00, 0x09,
+        0x30, 0x00, 0x06,
+        0x30, 0x00, 0x0d,
+        0x16, 0x00, 0xbe, 0xef,
+        0x16, 0x01, 0x00, 0x11,
+        0x01,
+    ]);
+}
+
+#[test]
+fn directives_emit_their_bytes_where_org_puts_them() {
+    let program = assemble("
+        .org 0x0100
+            .db 1, \"hi\", 0xff
+            .dw 0x1234, after
+            .string \"ok\"
+        after:
+            .fill 3, 0xaa
+            .fill 2
+        .org 0x0200
+            .db 7
+    ".to_owned()).unwrap();
+
+    let segments = program.segments.iter().map(|s| (s.origin, s.bytes.clone())).collect::<Vec<_>>();
+    assert_eq!(segments, [
+        (0x0100, vec![1, b'h', b'i', 0xff, 0x12, 0x34, 0x01, 0x0b, b'o', b'k', 0, 0xaa, 0xaa, 0xaa, 0, 0]),
+        (0x0200, vec![7]),
+    ]);
+}
+
+#[test]
+fn output_must_fit_in_memory_without_overlapping() {
+    for (source, line, token, message) in [
+        ("nop\n.org 0\nhlt", 3, "hlt", "output overlaps earlier code or data"),
+        (".fill 4\n.org 2\n.db 1", 3, ".db", "output overlaps earlier code or data"),
+        (".org 0xffff\n.dw 1", 2, ".dw", "output runs past the end of memory"),
+        (".org 0xfffe\nldi x, 1", 2, "ldi", "output runs past the end of memory"),
+    ] {
+        let found = errors(source).into_iter().map(|e| (e.line, e.token, e.message)).collect::<Vec<_>>();
+        assert_eq!(found, [(line, token.to_owned(), message.to_owned())], "{source:?}");
+    }
+
+    assert!(assemble(".org 0xffff\n.db 1".to_owned()).is_ok());
+}
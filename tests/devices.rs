@@ -0,0 +1,36 @@
+use pc_visualizer::sound::{Sound, Tone};
+use pc_visualizer::{assemble, Cpu};
+
+#[test]
+fn sound_ports_queue_tones_in_order() {
+    let mut cpu = Cpu::new();
+    assemble("
+        .equ FREQUENCY 0xfe50
+        .equ DURATION 0xfe52
+        .equ VOLUME 0xfe54
+            ldi x, 440
+            sto x, FREQUENCY
+            ldi x, 200
+            sto x, DURATION
+            ldi x, 64
+            sto x, VOLUME
+            ldi x, 880
+            sto x, FREQUENCY
+            ldi x, 50
+            sto x, DURATION
+            ldi x, 0
+            sto x, DURATION     ; no length, silence
+            sto x, FREQUENCY
+            ldi x, 10
+            sto x, DURATION     ; no frequency, silence
+            hlt
+    ".to_owned()).unwrap().load(&mut cpu);
+    cpu.run(100);
+
+    let sound = cpu.bus_mut().device_mut::<Sound>().unwrap();
+    assert_eq!(sound.take_tones(), [
+        Tone { frequency: 440, duration: 200, volume: 255 },
+        Tone { frequency: 880, duration: 50, volume: 64 },
+    ]);
+    assert_eq!(sound.take_tones(), []);
+}
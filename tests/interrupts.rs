@@ -0,0 +1,132 @@
+use pc_visualizer::cpu::STACK_TOP;
+use pc_visualizer::keyboard::Keyboard;
+use pc_visualizer::{assemble, Cpu, Flags};
+
+/// Where the test programs keep their handler counter.
+const COUNT: usize = 0x4000;
+
+fn run(source: &str, steps: usize) -> Cpu {
+    let mut cpu = Cpu::new();
+    assemble(source.to_owned()).unwrap().load(&mut cpu);
+    cpu.run(steps);
+    cpu
+}
+
+#[test]
+fn acknowledged_timer_irq_enters_the_handler_once_per_overflow() {
+    let cpu = run("
+        .equ RELOAD 0xfe10
+        .equ CONTROL 0xfe14
+        .equ COUNT 0x4000
+        .org 0xfff0
+        .dw tick
+        .org 0
+            ldi x, 20
+            sto x, RELOAD
+            ldi x, 3
+            sto x, CONTROL
+            ei
+        wait:
+            lod x, COUNT
+            ldi y, 3
+            cmp
+            jne wait
+            hlt
+        tick:
+            push x
+            lod x, COUNT
+            inc x
+            sto x, COUNT
+            ldi x, 3
+            sto x, CONTROL
+            pop x
+            iret
+    ", 1000);
+
+    assert!(cpu.halted(), "{:?}", cpu.fault());
+    assert_eq!(cpu.read_word(COUNT), Ok(3));
+}
+
+#[test]
+fn one_key_press_enters_the_keyboard_handler_once() {
+    let mut cpu = Cpu::new();
+    assemble("
+        .equ STATUS 0xfe20
+        .equ DATA 0xfe22
+        .equ COUNT 0x4000
+        .org 0xfff2
+        .dw key
+        .org 0
+            ldi x, 2
+            sto x, STATUS
+            ei
+        idle:
+            jmp idle
+        key:
+            push x
+            lod x, COUNT
+            inc x
+            sto x, COUNT
+            sto x, DATA
+            pop x
+            iret
+    ".to_owned()).unwrap().load(&mut cpu);
+    cpu.bus_mut().device_mut::<Keyboard>().unwrap().press(b'a');
+    cpu.run(200);
+
+    assert_eq!(cpu.fault(), None);
+    assert_eq!(cpu.read_word(COUNT), Ok(1));
+}
+
+#[test]
+fn di_holds_an_irq_until_ei_and_iret_restores_the_flags() {
+    let mut cpu = Cpu::new();
+    assemble("
+        .org 0xfff4
+        .dw handler
+        .org 0
+            ei
+            di
+            nop
+            ei
+            nop
+            hlt
+        handler:
+            scf
+            iret
+    ".to_owned()).unwrap().load(&mut cpu);
+    let flags = Flags { c: false, z: true, n: true, v: false };
+    cpu.set_flags(flags);
+
+    cpu.run(2);
+    cpu.raise(2);
+    cpu.run(2);
+    assert_eq!((cpu.pc(), cpu.interrupted()), (4, None), "masked by DI");
+    assert_eq!(cpu.pending(), 1 << 2);
+
+    cpu.step().unwrap();
+    assert_eq!((cpu.pc(), cpu.interrupted()), (6, Some(2)));
+    assert!(!cpu.interrupts_enabled());
+    assert_eq!(cpu.sp(), STACK_TOP - 4);
+
+    cpu.run(2);
+    assert!(cpu.interrupts_enabled());
+    assert_eq!((cpu.pc(), cpu.sp(), cpu.flags(), cpu.pending()), (4, STACK_TOP, flags, 0));
+
+    cpu.run(10);
+    assert!(cpu.halted());
+}
+
+#[test]
+fn irq_without_a_vector_is_dropped() {
+    let mut cpu = Cpu::new();
+    assemble("
+            ei
+            nop
+            hlt
+    ".to_owned()).unwrap().load(&mut cpu);
+    cpu.raise(3);
+
+    cpu.run(2);
+    assert_eq!((cpu.pc(), cpu.sp(), cpu.pending(), cpu.interrupted()), (2, STACK_TOP, 0, None));
+}
@@ -0,0 +1,141 @@
+use pc_visualizer::isa::{self, Flow, Operand, INSTRUCTIONS};
+use pc_visualizer::cpu::STACK_TOP;
+use pc_visualizer::{assemble, Cpu, Fault};
+
+fn source(inst: &isa::Instruction) -> String {
+    let operands = inst.operands.iter().map(|o| match o {
+        Operand::Register => "Y".to_owned(),
+        Operand::Value => "0x1234".to_owned(),
+        Operand::Pointer => "0x0ABC".to_owned(),
+        Operand::Indexed => "[X+0x0010]".to_owned(),
+    }).collect::<Vec<_>>();
+
+    if operands.is_empty() { inst.mnemonic.to_owned() }
+    else { format!("{} {}", inst.mnemonic, operands.join(", ")) }
+}
+
+/// Runs a program until it halts.
+fn run(source: &str) -> Cpu {
+    let mut cpu = Cpu::new();
+    assemble(source.to_owned()).unwrap().load(&mut cpu);
+    cpu.run(100);
+    assert!(cpu.halted(), "{:?}", cpu.fault());
+    cpu
+}
+
+#[test]
+fn every_instruction_round_trips() {
+    for inst in INSTRUCTIONS {
+        let text = source(inst);
+        let program = assemble(text.clone()).unwrap_or_else(|e| panic!("{text}: {e:?}"));
+        let bytes = &program.segments[0].bytes;
+
+        assert_eq!(bytes[0], inst.opcode, "{text}");
+        assert_eq!(bytes.len(), inst.size(), "{text}");
+        assert_eq!(isa::disassemble(bytes), Some((text.clone(), inst.size())));
+
+        assert_eq!(isa::by_opcode(inst.opcode).map(|i| i.mnemonic), Some(inst.mnemonic));
+        assert_eq!(isa::by_mnemonic(&inst.mnemonic.to_lowercase()).map(|i| i.mnemonic), Some(inst.mnemonic));
+    }
+}
+
+#[test]
+fn straight_line_instructions_advance_by_their_size() {
+    for inst in INSTRUCTIONS.iter().filter(|i| i.flow == Flow::Next) {
+        let mut cpu = Cpu::new();
+        cpu.load(0, &assemble(source(inst)).unwrap().segments[0].bytes);
+        cpu.set_x(6);
+        cpu.set_y(3);
+        cpu.set_sp(cpu.sp() - 2);
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.pc(), inst.size(), "{}", inst.mnemonic);
+    }
+}
+
+#[test]
+fn every_byte_of_an_instruction_is_checked_against_its_region() {
+    for (source, fault) in [
+        (".region noaccess 0x0001 0x0001\n inc x", Fault::NoAccess(1)),
+        (".region noaccess 0x0002 0x0002\n ldi x, 0x1234", Fault::NoAccess(2)),
+        (".region noaccess 0x0000 0x0000\n nop", Fault::NoAccess(0)),
+        (".region data 0x0000 0x0000\n nop", Fault::NotExecutable(0)),
+        (".region data 0x0001 0x0003\n ldi x, 0x1234", Fault::NotExecutable(1)),
+        (".region data 0x0003 0x0003\n ldi x, 0x1234", Fault::NotExecutable(3)),
+        (".region data 0x0002 0x0002\n lod x, [y+0x0010]", Fault::NotExecutable(2)),
+    ] {
+        let mut cpu = Cpu::new();
+        assemble(source.to_owned()).unwrap().load(&mut cpu);
+        assert_eq!(cpu.step(), Err(fault), "{source}");
+    }
+}
+
+#[test]
+fn overflows_report_the_first_address_past_memory() {
+    let mut cpu = Cpu::new();
+    cpu.write(0xfffe, isa::by_mnemonic("ldi").unwrap().opcode);
+    cpu.set_pc(0xfffe);
+    assert_eq!(cpu.step(), Err(Fault::BusOverflow(0x1_0000)));
+
+    let cpu = Cpu::new();
+    assert_eq!(cpu.read_word(0xffff), Err(Fault::BusOverflow(0x1_0000)));
+    assert_eq!(cpu.read_word(0x1_0004), Err(Fault::BusOverflow(0x1_0004)));
+    assert_eq!(cpu.read_word(0xfffe), Ok(0));
+}
+
+#[test]
+fn stores_to_rom_fault_and_leave_it_unchanged() {
+    let mut cpu = Cpu::new();
+    assemble(".region rom 0x4000 0x40ff\n ldi x, 0x1234\n sto x, 0x40ff".to_owned()).unwrap().load(&mut cpu);
+    let before = cpu.read(0x40ff);
+
+    cpu.run(2);
+    assert_eq!(cpu.fault(), Some(Fault::WriteToRom(0x40ff)));
+    assert_eq!(cpu.read(0x40ff), before);
+}
+
+#[test]
+fn indexed_operands_add_the_register_to_the_offset() {
+    let cpu = run("
+            ldi x, 0x4010
+            ldi y, 0x0010
+            ldi r2, 0xbeef
+            sto r2, [x+0x0010]
+            lod r3, [0x4010+y]
+            stb r2, [x-4]
+            ldb r4, [0x3ffc+y]
+            ldi x, 0x0002
+            sto r2, [x-4]
+            hlt
+    ");
+
+    assert_eq!(cpu.read_word(0x4020), Ok(0xbeef));
+    assert_eq!(cpu.reg(3), 0xbeef);
+    assert_eq!(cpu.read(0x400c), 0xef);
+    assert_eq!(cpu.reg(4), 0x00ef);
+    assert_eq!(cpu.read_word(0xfffe), Ok(0xbeef), "[X-4] wraps below 0000");
+}
+
+#[test]
+fn indexed_jumps_and_calls_go_to_the_register_plus_the_offset() {
+    let cpu = run("
+            ldi x, table
+            jmp [x+1]
+        table:
+            hlt
+            ldi y, 1
+            hlt
+    ");
+    assert_eq!(cpu.y(), 1);
+
+    let cpu = run("
+            ldi x, routine
+            call [x]
+            hlt
+        routine:
+            pop y
+            push y
+            ret
+    ");
+    assert_eq!((cpu.y(), cpu.pc(), cpu.sp()), (8, 8, STACK_TOP), "CALL [X] at 0004 returns to 0008");
+}
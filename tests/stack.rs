@@ -0,0 +1,86 @@
+use pc_visualizer::cpu::{STACK_LIMIT, STACK_TOP};
+use pc_visualizer::{assemble, Cpu, Fault};
+
+fn run(source: &str, steps: usize) -> Cpu {
+    let mut cpu = Cpu::new();
+    assemble(source.to_owned()).unwrap().load(&mut cpu);
+    cpu.run(steps);
+    cpu
+}
+
+#[test]
+fn runaway_recursion_faults_at_the_stack_limit() {
+    let cpu = run("
+            ldi x, 0x1234
+            sto x, 0xff7e
+        recurse:
+            call recurse
+    ", 1000);
+
+    assert_eq!(cpu.fault(), Some(Fault::StackOverflow));
+    assert_eq!(cpu.sp(), STACK_LIMIT);
+    assert_eq!(cpu.read_word(0xff7e), Ok(0x1234));
+    assert_eq!(cpu.read_word(0xfe00), Ok(0));
+}
+
+#[test]
+fn the_stack_holds_its_whole_depth() {
+    let depth = (STACK_TOP - STACK_LIMIT) as usize / 2;
+    let cpu = run(&"push x\n".repeat(depth), depth);
+    assert_eq!((cpu.fault(), cpu.sp()), (None, STACK_LIMIT));
+}
+
+#[test]
+fn call_pushes_the_next_address_and_ret_returns_to_it() {
+    let mut cpu = Cpu::new();
+    assemble("
+            call routine
+            ldi y, 2
+            hlt
+        routine:
+            ldi x, 1
+            ret
+    ".to_owned()).unwrap().load(&mut cpu);
+
+    cpu.step().unwrap();
+    assert_eq!((cpu.pc(), cpu.sp()), (8, STACK_TOP - 2));
+    assert_eq!(cpu.read_word(cpu.sp() as usize), Ok(3), "CALL at 0000 returns to 0003");
+
+    cpu.run(100);
+    assert!(cpu.halted(), "{:?}", cpu.fault());
+    assert_eq!((cpu.x(), cpu.y(), cpu.sp()), (1, 2, STACK_TOP));
+}
+
+#[test]
+fn pop_returns_pushed_words_last_first() {
+    let cpu = run("
+            ldi x, 0x1111
+            ldi y, 0x2222
+            push x
+            push y
+            pop x
+            pop y
+            hlt
+    ", 100);
+
+    assert!(cpu.halted(), "{:?}", cpu.fault());
+    assert_eq!((cpu.x(), cpu.y(), cpu.sp()), (0x2222, 0x1111, STACK_TOP));
+}
+
+#[test]
+fn popping_or_returning_from_an_empty_stack_underflows() {
+    for source in ["pop x", "ret", "push x\n pop x\n pop x"] {
+        let cpu = run(source, 100);
+        assert_eq!((cpu.fault(), cpu.sp()), (Some(Fault::StackUnderflow), STACK_TOP), "{source}");
+    }
+}
+
+#[test]
+fn a_push_past_the_limit_overflows_and_leaves_the_stack_pointer() {
+    let mut cpu = Cpu::new();
+    assemble("push x".to_owned()).unwrap().load(&mut cpu);
+    cpu.set_sp(STACK_LIMIT);
+
+    assert_eq!(cpu.step(), Err(Fault::StackOverflow));
+    assert_eq!((cpu.pc(), cpu.sp()), (0, STACK_LIMIT));
+}